@@ -0,0 +1,38 @@
+// frames per second at 1920x1080 for a range of thread counts: `cargo bench`
+
+use std::time::Instant;
+
+use twoderaycaster::{Framebuffer, RayCaster, Vector};
+
+const FRAMES: u32 = 30;
+
+fn main() {
+    let mut raycaster = RayCaster::new(60.).unwrap();
+    raycaster.set_camera(Vector::new(22., 12.), Vector::new(-1., 0.), 0.5);
+
+    let mut frame = Framebuffer::new(1920, 1080);
+    let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut threads = vec![1, 2, 4, 8, available];
+    threads.retain(|&n| n <= available);
+    threads.dedup();
+
+    let mut single = 0.;
+    for n in threads {
+        raycaster.set_threads(n);
+
+        // one untimed frame so textures and buffers are already in cache
+        raycaster.draw(&mut frame, false).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            raycaster.draw(&mut frame, false).unwrap();
+        }
+        let fps = FRAMES as f64 / start.elapsed().as_secs_f64();
+
+        if n == 1 {
+            single = fps;
+        }
+        println!("{:>2} threads: {:>7.1} fps ({:.2}x)", n, fps, fps / single);
+    }
+}
@@ -0,0 +1,116 @@
+use twoderaycaster::{assets_dir, Vector};
+
+pub const USAGE: &str = "\
+usage: twoderaycaster [options]
+
+  --map PATH                map to load: an image, a .txt text map or a Tiled .tmx/.json
+                            (default: assets/map.png)
+  --spawn X,Y[,DIR_X,DIR_Y] where the player starts and which way they face
+  --pitch P                 how far the view is tilted down, 0 to 1 (default: 0.5)
+  --fov DEGREES             horizontal field of view (default: 60)
+  --resolution WxH          size frames are drawn at, upscaled to the window
+  --no-vsync                draw as fast as possible instead of at the display rate
+  --render PATH             draw one frame to a PNG and exit, no window needed
+  --map-view                with --render, draw the top-down map instead
+  --minimap                 start with the minimap shown, also with --render
+  --help                    show this message";
+
+pub struct Options {
+    pub map: Option<String>,
+    pub spawn: Option<(Vector<f64>, Option<Vector<f64>>)>,
+    pub pitch: f64,
+    pub fov: f64,
+    pub resolution: Option<(u32, u32)>,
+    pub vsync: bool,
+    pub render: Option<String>,
+    pub map_view: bool,
+    pub minimap: bool,
+    pub help: bool,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            map: None,
+            spawn: None,
+            pitch: 0.5,
+            fov: 60.,
+            resolution: None,
+            vsync: true,
+            render: None,
+            map_view: false,
+            minimap: false,
+            help: false,
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", flag));
+
+            match flag.as_str() {
+                "--map" => options.map = Some(value()?.clone()),
+                "--spawn" => options.spawn = Some(parse_spawn(value()?)?),
+                "--pitch" => {
+                    options.pitch = parse_number(flag, value()?)?;
+                    if !(0. ..=1.).contains(&options.pitch) {
+                        return Err("--pitch must be between 0 and 1".to_string());
+                    }
+                }
+                "--fov" => {
+                    options.fov = parse_number(flag, value()?)?;
+                    if !(options.fov > 0. && options.fov < 180.) {
+                        return Err("--fov must be between 0 and 180 degrees".to_string());
+                    }
+                }
+                "--resolution" => options.resolution = Some(parse_resolution(value()?)?),
+                "--no-vsync" => options.vsync = false,
+                "--render" => options.render = Some(value()?.clone()),
+                "--map-view" => options.map_view = true,
+                "--minimap" => options.minimap = true,
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option {:?}, see --help", flag)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    // the map given on the command line, or the bundled one wherever it's run from
+    pub fn map_path(&self) -> String {
+        self.map
+            .clone()
+            .unwrap_or_else(|| format!("{}/map.png", assets_dir()))
+    }
+}
+
+fn parse_number(flag: &str, s: &str) -> Result<f64, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("bad {} {:?}, expected a number", flag, s))
+}
+
+fn parse_spawn(s: &str) -> Result<(Vector<f64>, Option<Vector<f64>>), String> {
+    let numbers = s
+        .split(',')
+        .map(|n| parse_number("--spawn", n))
+        .collect::<Result<Vec<f64>, _>>()?;
+
+    match numbers[..] {
+        [x, y] => Ok((Vector::new(x, y), None)),
+        [x, y, dir_x, dir_y] => Ok((Vector::new(x, y), Some(Vector::new(dir_x, dir_y)))),
+        _ => Err("--spawn needs X,Y or X,Y,DIR_X,DIR_Y".to_string()),
+    }
+}
+
+fn parse_resolution(s: &str) -> Result<(u32, u32), String> {
+    let err = || format!("bad --resolution {:?}, expected WIDTHxHEIGHT", s);
+
+    let (width, height) = s.split_once('x').ok_or_else(err)?;
+    let width: u32 = width.trim().parse().map_err(|_| err())?;
+    let height: u32 = height.trim().parse().map_err(|_| err())?;
+
+    if width == 0 || height == 0 {
+        return Err(err());
+    }
+    Ok((width, height))
+}
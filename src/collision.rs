@@ -0,0 +1,95 @@
+// Moving a circle through the grid. Walls push it back out the shortest way, which
+// slides it along them, and it moves in steps short enough that it can neither pass
+// through a wall nor squeeze between two walls that only touch at a corner.
+
+use crate::vector::Vector;
+
+// a point would slip through the edges between cells, so nothing is smaller than this
+const MIN_RADIUS: f64 = 0.01;
+
+// overlaps are resolved this many times per step, for corners where walls push back and forth
+const PASSES: usize = 4;
+
+// where a circle ends up after trying to move by motion; solid says whether a cell
+// blocks, which should include everything outside the map
+pub fn move_circle(
+    pos: Vector<f64>,
+    motion: Vector<f64>,
+    radius: f64,
+    solid: impl Fn(i32, i32) -> bool,
+) -> Vector<f64> {
+    let radius = radius.max(MIN_RADIUS);
+
+    // no step goes more than half way through a wall
+    let steps = (motion.length() / (radius / 2.)).ceil().max(1.);
+    let step = motion * (1. / steps);
+
+    let mut pos = pos;
+    for _ in 0..steps as usize {
+        pos += step;
+        for _ in 0..PASSES {
+            pos = push_out(pos, radius, &solid);
+        }
+    }
+    pos
+}
+
+// out of every solid cell the circle overlaps, deepest first so the corners where
+// neighbouring walls meet along a face don't push it sideways
+fn push_out(mut pos: Vector<f64>, radius: f64, solid: &impl Fn(i32, i32) -> bool) -> Vector<f64> {
+    let (left, right) = (
+        (pos.x - radius).floor() as i32,
+        (pos.x + radius).floor() as i32,
+    );
+    let (top, bottom) = (
+        (pos.y - radius).floor() as i32,
+        (pos.y + radius).floor() as i32,
+    );
+
+    let mut cells: Vec<(i32, i32)> = (top..=bottom)
+        .flat_map(|y| (left..=right).map(move |x| (x, y)))
+        .filter(|&(x, y)| solid(x, y))
+        .collect();
+    cells.sort_by(|&a, &b| gap(pos, a).total_cmp(&gap(pos, b)));
+
+    for (x, y) in cells {
+        let (x0, y0) = (x as f64, y as f64);
+        let nearest = nearest(pos, (x, y));
+        let offset = pos - nearest;
+        let distance = offset.length();
+
+        if distance >= radius {
+            continue;
+        } else if distance > 0. {
+            pos = nearest + offset * (radius / distance);
+            continue;
+        }
+
+        // inside, so out through the nearest edge
+        let edges = [
+            (pos.x - x0, Vector::new(x0 - radius, pos.y)),
+            (x0 + 1. - pos.x, Vector::new(x0 + 1. + radius, pos.y)),
+            (pos.y - y0, Vector::new(pos.x, y0 - radius)),
+            (y0 + 1. - pos.y, Vector::new(pos.x, y0 + 1. + radius)),
+        ];
+        if let Some(&(_, out)) = edges.iter().min_by(|a, b| a.0.total_cmp(&b.0)) {
+            pos = out;
+        }
+    }
+    pos
+}
+
+// the point of a cell nearest pos, which is pos itself when it's inside
+fn nearest(pos: Vector<f64>, (x, y): (i32, i32)) -> Vector<f64> {
+    let (x0, y0) = (x as f64, y as f64);
+    Vector::new(pos.x.clamp(x0, x0 + 1.), pos.y.clamp(y0, y0 + 1.))
+}
+
+// whether a circle overlaps a cell
+pub(crate) fn touches(pos: Vector<f64>, radius: f64, cell: (i32, i32)) -> bool {
+    gap(pos, cell) < radius.max(MIN_RADIUS)
+}
+
+fn gap(pos: Vector<f64>, cell: (i32, i32)) -> f64 {
+    (pos - nearest(pos, cell)).length()
+}
@@ -0,0 +1,136 @@
+use std::ops::Range;
+
+use crate::{framebuffer::Framebuffer, texture::Texture};
+
+// an RGBA frame stored column by column, so a vertical span is one contiguous run of
+// memory; frames are drawn into it and transposed into a Framebuffer at the end
+pub struct ColumnBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+// one column of a ColumnBuffer, top to bottom
+pub struct Column<'a> {
+    pixels: &'a mut [u8],
+}
+
+impl ColumnBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    pub fn columns_mut(&mut self) -> impl Iterator<Item = Column<'_>> {
+        self.pixels
+            .chunks_exact_mut(self.height * 4)
+            .map(|pixels| Column { pixels })
+    }
+
+    pub fn column_mut(&mut self, x: usize) -> Column<'_> {
+        let size = self.height * 4;
+        Column {
+            pixels: &mut self.pixels[x * size..(x + 1) * size],
+        }
+    }
+
+    // copies into the row-major frame in square tiles, so both sides stay in cache
+    pub fn transpose_into(&self, frame: &mut Framebuffer) {
+        const TILE: usize = 32;
+
+        let (width, height) = (self.width, self.height);
+        assert_eq!(
+            (frame.width() as usize, frame.height() as usize),
+            (width, height),
+            "frame and column buffer sizes differ"
+        );
+        let pixels = frame.pixels_mut();
+
+        for x0 in (0..width).step_by(TILE) {
+            for y0 in (0..height).step_by(TILE) {
+                for x in x0..(x0 + TILE).min(width) {
+                    let column = &self.pixels[x * height * 4..(x + 1) * height * 4];
+
+                    for y in y0..(y0 + TILE).min(height) {
+                        let index = (y * width + x) * 4;
+                        pixels[index..index + 4].copy_from_slice(&column[y * 4..y * 4 + 4]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Column<'_> {
+    pub fn set(&mut self, y: usize, color: [u8; 4]) {
+        self.pixels[y * 4..y * 4 + 4].copy_from_slice(&color);
+    }
+
+    pub fn fill(&mut self, rows: Range<usize>, color: [u8; 4]) {
+        for pixel in self.pixels[rows.start * 4..rows.end * 4].chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    // one column of texture stretched over rows, where row top is v = 0 and each unit
+    // rows is one repeat of the texture, with every channel scaled by shade
+    pub fn copy_strip(
+        &mut self,
+        rows: Range<usize>,
+        texture: &Texture,
+        u: f64,
+        top: f64,
+        unit: f64,
+        shade: f64,
+    ) {
+        self.strip(rows, texture, u, top, unit, shade, false);
+    }
+
+    // the same as copy_strip, but fully transparent texels leave the column untouched
+    pub fn blend_strip(
+        &mut self,
+        rows: Range<usize>,
+        texture: &Texture,
+        u: f64,
+        top: f64,
+        unit: f64,
+        shade: f64,
+    ) {
+        self.strip(rows, texture, u, top, unit, shade, true);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn strip(
+        &mut self,
+        rows: Range<usize>,
+        texture: &Texture,
+        u: f64,
+        top: f64,
+        unit: f64,
+        shade: f64,
+        skip_transparent: bool,
+    ) {
+        let texels = texture.column(u);
+        let texture_height = texture.height() as usize;
+        let start = rows.start;
+
+        for (i, pixel) in self.pixels[rows.start * 4..rows.end * 4]
+            .chunks_exact_mut(4)
+            .enumerate()
+        {
+            let v = ((start + i) as f64 - top) / unit;
+            let ty = ((v.rem_euclid(1.) * texture_height as f64) as usize).min(texture_height - 1);
+            let texel = &texels[ty * 4..ty * 4 + 4];
+
+            if skip_transparent && texel[3] == 0 {
+                continue;
+            }
+            for (channel, texel) in pixel.iter_mut().zip(texel) {
+                *channel = (*texel as f64 * shade) as u8;
+            }
+        }
+    }
+}
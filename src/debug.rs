@@ -0,0 +1,117 @@
+// A top-down view of the rays a frame casts, for seeing what the DDA does: the map
+// scaled to fit, the cells the drawn rays stepped through outlined, and every nth ray
+// from the player to where it stopped, colored by the kind of face it ended on.
+
+use std::collections::HashSet;
+
+use crate::{
+    draw::{filled_rectangle, line},
+    framebuffer::Framebuffer,
+    map::MapCellType,
+    raycaster::{RayCaster, Side},
+    vector::Vector,
+};
+
+const BACKGROUND: [u8; 4] = [16, 16, 20, 255];
+const EMPTY: [u8; 4] = [40, 40, 46, 255];
+const VISITED: [u8; 4] = [255, 220, 120, 255];
+const PLAYER: [u8; 4] = [255, 255, 255, 255];
+
+// rays that end on an x-side, a y-side, or nothing at all
+const X_SIDE: [u8; 4] = [255, 90, 70, 255];
+const Y_SIDE: [u8; 4] = [70, 170, 255, 255];
+const MISS: [u8; 4] = [150, 150, 150, 255];
+
+// draws the rays a frame the size of this one would cast, every nth of them
+pub fn draw_rays(raycaster: &RayCaster, frame: &mut Framebuffer, every: usize) {
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    if width == 0 || height == 0 {
+        return;
+    }
+    filled_rectangle(frame, 0, 0, width - 1, height - 1, BACKGROUND, 1);
+
+    let map = raycaster.map();
+    let columns = map.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let rows = map.len().max(1);
+    let cell = (width / columns).min(height / rows).max(1);
+    let left = width.saturating_sub(cell * columns) / 2;
+    let top = height.saturating_sub(cell * rows) / 2;
+
+    // a map position in frame pixels
+    let point = |pos: Vector<f64>| {
+        (
+            (left as f64 + pos.x * cell as f64) as isize,
+            (top as f64 + pos.y * cell as f64) as isize,
+        )
+    };
+
+    let gap = usize::from(cell >= 4);
+    for (y, row) in map.iter().enumerate() {
+        for (x, c) in row.iter().enumerate() {
+            let color = match c.solid {
+                MapCellType::Empty => EMPTY,
+                _ => c.color,
+            };
+            let (x, y) = (left + x * cell, top + y * cell);
+            filled_rectangle(
+                frame,
+                x,
+                y,
+                x + cell - 1 - gap,
+                y + cell - 1 - gap,
+                color,
+                1,
+            );
+        }
+    }
+
+    let traces: Vec<_> = raycaster
+        .trace(width, height)
+        .into_iter()
+        .step_by(every.max(1))
+        .collect();
+
+    let visited: HashSet<(i32, i32)> = traces
+        .iter()
+        .flat_map(|trace| trace.visited.iter().map(|cell| (cell.x, cell.y)))
+        .collect();
+    for (x, y) in visited {
+        let (x, y) = point(Vector::new(x as f64, y as f64));
+        let (x2, y2) = (x + cell as isize - 1, y + cell as isize - 1);
+        line(frame, x, y, x2, y, VISITED, 1);
+        line(frame, x, y2, x2, y2, VISITED, 1);
+        line(frame, x, y, x, y2, VISITED, 1);
+        line(frame, x2, y, x2, y2, VISITED, 1);
+    }
+
+    // a small square around a point
+    let mark = |frame: &mut Framebuffer, (x, y): (isize, isize), color| {
+        let (x, y) = (x.max(1) as usize - 1, y.max(1) as usize - 1);
+        filled_rectangle(frame, x, y, x + 2, y + 2, color, 1);
+    };
+
+    let origin = point(raycaster.eye());
+    for trace in &traces {
+        // the last wall is the one that stopped it, unless it got away
+        let color = match trace.hits.last() {
+            Some(hit) if hit.distance >= trace.reach => match hit.side {
+                Side::X => X_SIDE,
+                Side::Y => Y_SIDE,
+            },
+            _ => MISS,
+        };
+        let (x, y) = point(trace.origin + trace.dir * trace.reach);
+        line(frame, origin.0, origin.1, x, y, color, 1);
+
+        // every wall it hit gets a mark, short ones it carried on over included
+        for hit in &trace.hits {
+            let color = match hit.side {
+                Side::X => X_SIDE,
+                Side::Y => Y_SIDE,
+            };
+            mark(frame, point(hit.point), color);
+        }
+    }
+
+    mark(frame, origin, PLAYER);
+}
@@ -0,0 +1,84 @@
+use crate::framebuffer::Framebuffer;
+
+fn verline(frame: &mut Framebuffer, x: usize, y1: usize, y2: usize, rgba: [u8; 4], scale: usize) {
+    for y in (y1 * scale)..=(y2 * scale) {
+        set_pixel(frame, x, y, rgba, scale);
+    }
+}
+
+pub fn line(
+    frame: &mut Framebuffer,
+    x1: isize,
+    y1: isize,
+    x2: isize,
+    y2: isize,
+    color: [u8; 4],
+    scale: usize,
+) {
+    if x1 == x2 {
+        verline(frame, x1 as usize, y1 as usize, y2 as usize, color, scale);
+        return;
+    }
+    let dx = isize::abs(x2 - x1) * scale as isize;
+    let sx = if x1 < x2 { 1 } else { -1 };
+    let dy = -isize::abs(y2 - y1) * scale as isize;
+    let sy = if y1 < y2 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut x = x1 * scale as isize;
+    let mut y = y1 * scale as isize;
+
+    loop {
+        set_pixel(frame, x as usize, y as usize, color, scale);
+
+        if x == x2 * scale as isize && y == y2 * scale as isize {
+            break;
+        }
+
+        let e2 = 2 * err;
+
+        if e2 >= dy {
+            err += dy;
+            x += sx * scale as isize;
+        }
+
+        if e2 <= dx {
+            err += dx;
+            y += sy * scale as isize;
+        }
+    }
+}
+
+pub fn filled_rectangle(
+    frame: &mut Framebuffer,
+    x1: usize,
+    y1: usize,
+    x2: usize,
+    y2: usize,
+    color: [u8; 4],
+    scale: usize,
+) {
+    for x in (x1 * scale)..=(x2 * scale) {
+        for y in (y1 * scale)..=(y2 * scale) {
+            if x >= frame.width() as usize || y >= frame.height() as usize {
+                continue;
+            }
+            set_pixel(frame, x, y, color, scale);
+        }
+    }
+}
+
+pub fn set_pixel(frame: &mut Framebuffer, x: usize, y: usize, color: [u8; 4], scale: usize) {
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let pixels = frame.pixels_mut();
+
+    for i in 0..scale {
+        for j in 0..scale {
+            let xi = x * scale + i;
+            let yj = y * scale + j;
+            if xi < width && yj < height {
+                let index = (yj * width + xi) * 4;
+                pixels[index..index + 4].copy_from_slice(&color);
+            }
+        }
+    }
+}
@@ -0,0 +1,591 @@
+// A map editor drawn top-down over the whole frame: the map scaled to fit, the spawn
+// and the player on it, and a palette of walls along the bottom with a gauge for the
+// height walls are painted at. Each stroke of painting is undone and redone as one.
+
+use crate::{
+    draw::{filled_rectangle, line, set_pixel},
+    error::{Error, Result},
+    framebuffer::Framebuffer,
+    map::{save_map, Map, MapCell, MapCellType, Surface},
+    raycaster::RayCaster,
+    texture::Texture,
+    vector::Vector,
+};
+
+// plain walls offered after the map's own and one for each texture in the atlas
+const COLORS: [[u8; 4]; 4] = [
+    [200, 60, 50, 255],
+    [70, 160, 80, 255],
+    [60, 90, 200, 255],
+    [230, 230, 230, 255],
+];
+
+// painted walls go up and down in these steps, as high as a heightmap can store
+pub const HEIGHT_STEP: f64 = 0.25;
+pub const MAX_HEIGHT: f64 = 3.75;
+
+const BACKGROUND: [u8; 4] = [16, 16, 20, 255];
+const EMPTY: [u8; 4] = [40, 40, 46, 255];
+const HIGHLIGHT: [u8; 4] = [255, 255, 255, 255];
+const SPAWN: [u8; 4] = [60, 140, 255, 255];
+const PLAYER: [u8; 4] = [255, 40, 40, 255];
+const DOOR: [u8; 4] = [150, 110, 60, 255];
+
+// what's under a point of the frame
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pick {
+    Cell(usize, usize),
+    Swatch(usize),
+}
+
+#[derive(Clone, Copy)]
+enum Change {
+    Cell {
+        x: usize,
+        y: usize,
+        before: MapCell,
+        after: MapCell,
+    },
+    Spawn {
+        before: Vector<f64>,
+        after: Vector<f64>,
+    },
+}
+
+pub struct Editor {
+    // the name, markers and sprites to save with the cells, which the raycaster holds
+    map: Map,
+    palette: Vec<MapCell>,
+    selected: usize,
+    height: f64,
+    spawn: Vector<f64>,
+    dir: Vector<f64>,
+    hover: Option<Pick>,
+    stroke: Vec<Change>,
+    undo: Vec<Vec<Change>>,
+    redo: Vec<Vec<Change>>,
+}
+
+// where everything goes in a frame of a given size
+struct Layout {
+    cell: usize,
+    left: usize,
+    top: usize,
+    swatch: usize,
+    palette_top: usize,
+}
+
+impl Editor {
+    // edits the raycaster's map, starting from where the player is now
+    pub fn new(raycaster: &RayCaster, map: Map) -> Self {
+        let palette = palette(raycaster);
+        let height = palette
+            .first()
+            .map_or(1., |cell| cell.height.clamp(HEIGHT_STEP, MAX_HEIGHT));
+
+        Self {
+            map: Map {
+                cells: Vec::new(),
+                ..map
+            },
+            palette,
+            selected: 0,
+            height,
+            spawn: raycaster.position(),
+            dir: raycaster.direction(),
+            hover: None,
+            stroke: Vec::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn palette(&self) -> &[MapCell] {
+        &self.palette
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    // picks a palette entry, and paints at its height from then on, as near as the
+    // brush goes
+    pub fn select(&mut self, i: usize) {
+        if let Some(cell) = self.palette.get(i) {
+            self.selected = i;
+            self.height = cell.height.clamp(HEIGHT_STEP, MAX_HEIGHT);
+        }
+    }
+
+    // moves through the palette, wrapping around at either end
+    pub fn cycle(&mut self, steps: isize) {
+        let len = self.palette.len() as isize;
+        self.select((self.selected as isize + steps).rem_euclid(len) as usize);
+    }
+
+    pub fn brush_height(&self) -> f64 {
+        self.height
+    }
+
+    pub fn adjust_height(&mut self, steps: i32) {
+        self.height = (self.height + steps as f64 * HEIGHT_STEP).clamp(HEIGHT_STEP, MAX_HEIGHT);
+    }
+
+    // the wall painted with
+    pub fn brush(&self) -> MapCell {
+        MapCell {
+            height: self.height,
+            ..self.palette[self.selected]
+        }
+    }
+
+    pub fn spawn(&self) -> (Vector<f64>, Vector<f64>) {
+        (self.spawn, self.dir)
+    }
+
+    pub fn pick(
+        &self,
+        raycaster: &RayCaster,
+        frame_size: (u32, u32),
+        x: usize,
+        y: usize,
+    ) -> Option<Pick> {
+        let map = raycaster.map();
+        let layout = Layout::new(map, self.palette.len(), frame_size);
+
+        if y >= layout.palette_top {
+            let i = x / layout.swatch;
+            return (i < self.palette.len()).then_some(Pick::Swatch(i));
+        }
+
+        let cx = x.checked_sub(layout.left)? / layout.cell;
+        let cy = y.checked_sub(layout.top)? / layout.cell;
+        map.get(cy)?.get(cx).map(|_| Pick::Cell(cx, cy))
+    }
+
+    // highlights what the cursor is over
+    pub fn hover(&mut self, pick: Option<Pick>) {
+        self.hover = pick;
+    }
+
+    // puts the brush in a cell, keeping its floor and ceiling; walls can't go where
+    // the player is or starts, and false means nothing changed
+    pub fn paint(&mut self, raycaster: &mut RayCaster, x: usize, y: usize) -> bool {
+        let brush = self.brush();
+        self.change(raycaster, x, y, |cell| MapCell {
+            floor: cell.floor,
+            ceiling: cell.ceiling,
+            ..brush
+        })
+    }
+
+    pub fn erase(&mut self, raycaster: &mut RayCaster, x: usize, y: usize) -> bool {
+        self.change(raycaster, x, y, |cell| MapCell {
+            floor: cell.floor,
+            ceiling: cell.ceiling,
+            ..MapCell::empty()
+        })
+    }
+
+    fn change(
+        &mut self,
+        raycaster: &mut RayCaster,
+        x: usize,
+        y: usize,
+        edit: impl Fn(MapCell) -> MapCell,
+    ) -> bool {
+        let before = match raycaster.map().get(y).and_then(|row| row.get(x)) {
+            Some(&cell) => cell,
+            None => return false,
+        };
+        let after = edit(before);
+
+        let occupied = [raycaster.position(), self.spawn]
+            .iter()
+            .any(|pos| (pos.x.floor(), pos.y.floor()) == (x as f64, y as f64));
+        if before == after || (after.solid != MapCellType::Empty && occupied) {
+            return false;
+        }
+
+        raycaster.set_cell(x, y, after);
+        self.stroke.push(Change::Cell {
+            x,
+            y,
+            before,
+            after,
+        });
+        true
+    }
+
+    // the player starts in the middle of the cell from now on, facing the same way
+    pub fn move_spawn(&mut self, raycaster: &RayCaster, x: usize, y: usize) -> Result<()> {
+        let pos = Vector::new(x as f64 + 0.5, y as f64 + 0.5);
+        let reason = match raycaster.map().get(y).and_then(|row| row.get(x)) {
+            None => Some("it's outside the map"),
+            Some(cell) if cell.solid != MapCellType::Empty => Some("it's inside a wall"),
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            return Err(Error::InvalidSpawn {
+                x: pos.x,
+                y: pos.y,
+                reason,
+            });
+        }
+
+        self.end_stroke();
+        self.stroke.push(Change::Spawn {
+            before: self.spawn,
+            after: pos,
+        });
+        self.spawn = pos;
+        self.end_stroke();
+        Ok(())
+    }
+
+    // everything painted since the last call is undone as one
+    pub fn end_stroke(&mut self) {
+        if !self.stroke.is_empty() {
+            self.undo.push(std::mem::take(&mut self.stroke));
+            self.redo.clear();
+        }
+    }
+
+    // false if there was nothing to undo
+    pub fn undo(&mut self, raycaster: &mut RayCaster) -> bool {
+        self.end_stroke();
+        match self.undo.pop() {
+            Some(changes) => {
+                for change in changes.iter().rev() {
+                    self.apply(raycaster, change, true);
+                }
+                self.redo.push(changes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self, raycaster: &mut RayCaster) -> bool {
+        self.end_stroke();
+        match self.redo.pop() {
+            Some(changes) => {
+                for change in &changes {
+                    self.apply(raycaster, change, false);
+                }
+                self.undo.push(changes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn apply(&mut self, raycaster: &mut RayCaster, change: &Change, backwards: bool) {
+        match *change {
+            Change::Cell {
+                x,
+                y,
+                before,
+                after,
+            } => {
+                raycaster.set_cell(x, y, if backwards { before } else { after });
+            }
+            Change::Spawn { before, after } => {
+                self.spawn = if backwards { before } else { after };
+            }
+        }
+    }
+
+    // the map as it stands, with the spawn and whatever it was loaded with
+    pub fn to_map(&self, raycaster: &RayCaster) -> Map {
+        Map {
+            cells: raycaster.map().to_vec(),
+            spawn: Some((self.spawn, Some(self.dir))),
+            ..self.map.clone()
+        }
+    }
+
+    pub fn save(&self, raycaster: &RayCaster, path: &str) -> Result<()> {
+        save_map(&self.to_map(raycaster), path)
+    }
+
+    pub fn draw(&self, raycaster: &RayCaster, frame: &mut Framebuffer) {
+        let map = raycaster.map();
+        let layout = Layout::new(map, self.palette.len(), (frame.width(), frame.height()));
+        let cell = layout.cell;
+
+        rectangle(
+            frame,
+            0,
+            0,
+            frame.width() as usize,
+            frame.height() as usize,
+            BACKGROUND,
+        );
+
+        // a gap between cells once they're big enough to spare it
+        let gap = usize::from(cell >= 4);
+        for (y, row) in map.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                let color = match (c.solid, c.floor) {
+                    (MapCellType::Empty, Some(Surface::Color(color))) => color,
+                    (MapCellType::Empty, _) => EMPTY,
+                    // shorter walls are darker
+                    _ => shade(c.color, 0.4 + 0.6 * c.height.min(1.)),
+                };
+                let (left, top) = (layout.left + x * cell, layout.top + y * cell);
+                rectangle(frame, left, top, cell - gap, cell - gap, color);
+                mark(frame, c.solid, left, top, cell - gap);
+            }
+        }
+
+        if let Some(Pick::Cell(x, y)) = self.hover {
+            let (left, top) = (layout.left + x * cell, layout.top + y * cell);
+            outline(frame, left, top, cell, cell, HIGHLIGHT);
+        }
+
+        // the spawn as a square facing its direction, the player as a dot
+        let (x, y) = layout.point(self.spawn);
+        let size = (cell / 2).max(1);
+        let corner = |v: usize| v.saturating_sub(size / 2);
+        rectangle(frame, corner(x), corner(y), size, size, SPAWN);
+        line(
+            frame,
+            x as isize,
+            y as isize,
+            (x as f64 + self.dir.x / self.dir.length() * cell as f64) as isize,
+            (y as f64 + self.dir.y / self.dir.length() * cell as f64) as isize,
+            SPAWN,
+            1,
+        );
+        let (x, y) = layout.point(raycaster.position());
+        let size = (cell / 4).max(1);
+        let corner = |v: usize| v.saturating_sub(size / 2);
+        rectangle(frame, corner(x), corner(y), size, size, PLAYER);
+
+        self.draw_palette(raycaster.textures(), frame, &layout);
+    }
+
+    fn draw_palette(&self, textures: &[Texture], frame: &mut Framebuffer, layout: &Layout) {
+        let (size, top) = (layout.swatch, layout.palette_top);
+        let inset = usize::from(size >= 6);
+
+        for (i, cell) in self.palette.iter().enumerate() {
+            let left = i * size;
+
+            match cell.texture.and_then(|id| textures.get(id)) {
+                Some(texture) => {
+                    for y in inset..size - inset {
+                        for x in inset..size - inset {
+                            let (u, v) = (x as f64 / size as f64, y as f64 / size as f64);
+                            set_pixel(frame, left + x, top + y, texture.sample(u, v), 1);
+                        }
+                    }
+                }
+                None => rectangle(
+                    frame,
+                    left + inset,
+                    top + inset,
+                    size - 2 * inset,
+                    size - 2 * inset,
+                    cell.color,
+                ),
+            }
+
+            mark(
+                frame,
+                cell.solid,
+                left + inset,
+                top + inset,
+                size - 2 * inset,
+            );
+
+            if i == self.selected || self.hover == Some(Pick::Swatch(i)) {
+                outline(frame, left, top, size, size, HIGHLIGHT);
+            }
+        }
+
+        // the brush height fills the square after the palette from the bottom up
+        let left = self.palette.len() * size;
+        let filled = (size as f64 * self.height / MAX_HEIGHT).round() as usize;
+        outline(frame, left, top, size, size, EMPTY);
+        rectangle(
+            frame,
+            left + inset,
+            top + size - filled,
+            size - 2 * inset,
+            filled,
+            HIGHLIGHT,
+        );
+    }
+}
+
+impl Layout {
+    fn new(map: &[Vec<MapCell>], palette: usize, (width, height): (u32, u32)) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        let columns = map.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        let rows = map.len().max(1);
+
+        // the palette and height gauge are a row of squares along the bottom
+        let swatch = (height / 10).min(width / (palette + 1)).max(1);
+        let palette_top = height.saturating_sub(swatch);
+        let cell = (width / columns).min(palette_top / rows).max(1);
+
+        Self {
+            cell,
+            left: width.saturating_sub(cell * columns) / 2,
+            top: palette_top.saturating_sub(cell * rows) / 2,
+            swatch,
+            palette_top,
+        }
+    }
+
+    // a map position in frame pixels
+    fn point(&self, pos: Vector<f64>) -> (usize, usize) {
+        (
+            (self.left as f64 + pos.x * self.cell as f64).max(0.) as usize,
+            (self.top as f64 + pos.y * self.cell as f64).max(0.) as usize,
+        )
+    }
+}
+
+// the map's own kinds of wall, then every texture, then a few plain colors
+fn palette(raycaster: &RayCaster) -> Vec<MapCell> {
+    let walls = raycaster
+        .map()
+        .iter()
+        .flatten()
+        .filter(|cell| cell.solid != MapCellType::Empty)
+        .copied();
+    let textured =
+        raycaster.textures().iter().enumerate().map(|(i, texture)| {
+            MapCell::new(average(texture), MapCellType::Wall, 1.).with_texture(i)
+        });
+    let plain = COLORS
+        .iter()
+        .map(|&color| MapCell::new(color, MapCellType::Wall, 1.));
+
+    let mut palette: Vec<MapCell> = Vec::new();
+    for cell in walls.chain(textured).chain(plain) {
+        let cell = MapCell {
+            floor: None,
+            ceiling: None,
+            ..cell
+        };
+        // walls that look alike are one entry, at the first height seen
+        let alike = |c: &MapCell| {
+            c.solid == cell.solid
+                && c.texture == cell.texture
+                && (cell.texture.is_some() || c.color == cell.color)
+        };
+        if !palette.iter().any(alike) {
+            palette.push(cell);
+        }
+    }
+
+    // a door and a push wall that looks like the first wall, unless the map has its own
+    let kinds = [
+        MapCell::new(DOOR, MapCellType::Door, 1.),
+        MapCell {
+            solid: MapCellType::PushWall,
+            ..palette[0]
+        },
+    ];
+    for cell in kinds {
+        if !palette.iter().any(|c| c.solid == cell.solid) {
+            palette.push(cell);
+        }
+    }
+    palette
+}
+
+// doors get a frame inside them and push walls a hole, drawn over a square of size
+fn mark(frame: &mut Framebuffer, kind: MapCellType, left: usize, top: usize, size: usize) {
+    if size < 6 {
+        return;
+    }
+    let inset = size / 4;
+    match kind {
+        MapCellType::Door => outline(
+            frame,
+            left + inset,
+            top + inset,
+            size - 2 * inset,
+            size - 2 * inset,
+            BACKGROUND,
+        ),
+        MapCellType::PushWall => rectangle(
+            frame,
+            left + size / 2 - size / 6,
+            top + size / 2 - size / 6,
+            size / 3,
+            size / 3,
+            BACKGROUND,
+        ),
+        _ => {}
+    }
+}
+
+// what a texture looks like from far away, for the cells painted with it
+fn average(texture: &Texture) -> [u8; 4] {
+    let mut sum = [0u64; 3];
+    for y in 0..texture.height() {
+        for x in 0..texture.width() {
+            for (sum, channel) in sum.iter_mut().zip(texture.get(x, y)) {
+                *sum += channel as u64;
+            }
+        }
+    }
+
+    let count = (texture.width() as u64 * texture.height() as u64).max(1);
+    [
+        (sum[0] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[2] / count) as u8,
+        255,
+    ]
+}
+
+fn shade(color: [u8; 4], factor: f64) -> [u8; 4] {
+    [
+        (color[0] as f64 * factor) as u8,
+        (color[1] as f64 * factor) as u8,
+        (color[2] as f64 * factor) as u8,
+        255,
+    ]
+}
+
+fn rectangle(
+    frame: &mut Framebuffer,
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+    color: [u8; 4],
+) {
+    if width > 0 && height > 0 {
+        filled_rectangle(
+            frame,
+            left,
+            top,
+            left + width - 1,
+            top + height - 1,
+            color,
+            1,
+        );
+    }
+}
+
+fn outline(
+    frame: &mut Framebuffer,
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+    color: [u8; 4],
+) {
+    rectangle(frame, left, top, width, 1, color);
+    rectangle(frame, left, top + height - 1, width, 1, color);
+    rectangle(frame, left, top, 1, height, color);
+    rectangle(frame, left + width - 1, top, 1, height, color);
+}
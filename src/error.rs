@@ -0,0 +1,115 @@
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    // a map, texture or output file couldn't be read or written
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    // a file was read but isn't an image the image crate understands, or couldn't be encoded
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    // an image loaded fine but doesn't describe a usable map
+    InvalidMap {
+        path: PathBuf,
+        reason: String,
+    },
+    // a texture atlas isn't a strip of square tiles
+    InvalidAtlas {
+        path: PathBuf,
+        reason: String,
+    },
+    // the player can't start at this position
+    InvalidSpawn {
+        x: f64,
+        y: f64,
+        reason: &'static str,
+    },
+    // a thread drawing part of the frame panicked
+    Render(String),
+    Window(winit::error::OsError),
+    CursorGrab(winit::error::ExternalError),
+    Surface(pixels::Error),
+    SurfaceResize(pixels::TextureError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // image errors that are really I/O errors are reported as such, so a missing file
+    // reads as a missing file
+    pub(crate) fn image(path: impl Into<PathBuf>, source: image::ImageError) -> Self {
+        let path = path.into();
+        match source {
+            image::ImageError::IoError(source) => Error::Io { path, source },
+            source => Error::Image { path, source },
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Image { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::InvalidMap { path, reason } => {
+                write!(f, "{}: invalid map, {}", path.display(), reason)
+            }
+            Error::InvalidAtlas { path, reason } => {
+                write!(f, "{}: invalid texture atlas, {}", path.display(), reason)
+            }
+            Error::InvalidSpawn { x, y, reason } => {
+                write!(f, "can't spawn at ({}, {}), {}", x, y, reason)
+            }
+            Error::Render(reason) => write!(f, "rendering failed, {}", reason),
+            Error::Window(e) => write!(f, "couldn't create the window: {}", e),
+            Error::CursorGrab(e) => write!(f, "couldn't grab the cursor: {}", e),
+            Error::Surface(e) => write!(f, "couldn't draw to the window: {}", e),
+            Error::SurfaceResize(e) => write!(f, "couldn't resize the window surface: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Image { source, .. } => Some(source),
+            Error::Window(e) => Some(e),
+            Error::CursorGrab(e) => Some(e),
+            Error::Surface(e) => Some(e),
+            Error::SurfaceResize(e) => Some(e),
+            Error::InvalidMap { .. }
+            | Error::InvalidAtlas { .. }
+            | Error::InvalidSpawn { .. }
+            | Error::Render(_) => None,
+        }
+    }
+}
+
+impl From<winit::error::OsError> for Error {
+    fn from(e: winit::error::OsError) -> Self {
+        Error::Window(e)
+    }
+}
+
+impl From<winit::error::ExternalError> for Error {
+    fn from(e: winit::error::ExternalError) -> Self {
+        Error::CursorGrab(e)
+    }
+}
+
+impl From<pixels::Error> for Error {
+    fn from(e: pixels::Error) -> Self {
+        Error::Surface(e)
+    }
+}
+
+impl From<pixels::TextureError> for Error {
+    fn from(e: pixels::TextureError) -> Self {
+        Error::SurfaceResize(e)
+    }
+}
@@ -0,0 +1,66 @@
+use image::RgbaImage;
+
+use crate::error::{Error, Result};
+
+// an RGBA frame in plain memory, laid out like the `pixels` frame
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+    }
+
+    // contents are cleared, since the old rows no longer line up
+    pub fn resize(&mut self, width: u32, height: u32) {
+        *self = Self::new(width, height);
+    }
+
+    // alpha is blended over black, the same as the window does
+    pub fn to_image(&self) -> RgbaImage {
+        let mut pixels = self.pixels.clone();
+
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u32;
+            for channel in &mut pixel[..3] {
+                *channel = (*channel as u32 * alpha / 255) as u8;
+            }
+            pixel[3] = 255;
+        }
+
+        RgbaImage::from_raw(self.width, self.height, pixels).unwrap()
+    }
+
+    pub fn save_png(&self, path: &str) -> Result<()> {
+        self.to_image()
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| Error::image(path, e))
+    }
+}
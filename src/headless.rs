@@ -0,0 +1,23 @@
+use crate::{error::Result, framebuffer::Framebuffer, raycaster::RayCaster};
+
+// draws one frame on the CPU, no window or GPU surface needed
+pub fn render(
+    raycaster: &RayCaster,
+    map_toggle: bool,
+    width: u32,
+    height: u32,
+) -> Result<Framebuffer> {
+    let mut frame = Framebuffer::new(width, height);
+    raycaster.draw(&mut frame, map_toggle)?;
+    Ok(frame)
+}
+
+pub fn render_to_png(
+    raycaster: &RayCaster,
+    map_toggle: bool,
+    width: u32,
+    height: u32,
+    path: &str,
+) -> Result<()> {
+    render(raycaster, map_toggle, width, height)?.save_png(path)
+}
@@ -0,0 +1,55 @@
+//! A grid raycaster that draws into plain RGBA buffers, so it can be used behind a
+//! window, headless, or from other tools.
+
+pub mod collision;
+mod columns;
+pub mod debug;
+pub mod draw;
+pub mod editor;
+pub mod error;
+pub mod framebuffer;
+pub mod headless;
+pub mod map;
+pub mod minimap;
+pub mod movement;
+pub mod raycaster;
+pub mod sprite;
+pub mod textmap;
+pub mod texture;
+pub mod tiled;
+pub mod vector;
+
+pub use collision::move_circle;
+pub use draw::{filled_rectangle, line, set_pixel};
+pub use editor::{Editor, Pick};
+pub use error::{Error, Result};
+pub use framebuffer::Framebuffer;
+pub use map::{
+    generate_map, load_map, open_borders, save_map, save_map_png, Map, MapCell, MapCellType,
+    Surface,
+};
+pub use minimap::Minimap;
+pub use movement::{Gait, MovementController};
+pub use raycaster::{Direction, Hit, Projection, RayCaster, Side, Trace};
+pub use sprite::Sprite;
+pub use textmap::{load_text_map, save_text_map};
+pub use tiled::load_tiled;
+pub use vector::Vector;
+
+// where the bundled map and texture atlases were at build time, which is only right on
+// the machine that built it, so for tests and as a last resort
+pub const ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets");
+
+// the bundled assets: next to the executable, then under the working directory, then
+// wherever they were built from
+pub fn assets_dir() -> String {
+    let beside_exe = std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join("assets")));
+    beside_exe
+        .into_iter()
+        .chain([std::path::PathBuf::from("assets")])
+        .find(|dir| dir.join("textures.png").is_file())
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_else(|| ASSETS.to_string())
+}
@@ -1,46 +1,142 @@
-use pixels::Error;
+use std::{path::Path, time::Instant};
+
 use winit::{
     event::{DeviceEvent, Event, VirtualKeyCode, WindowEvent},
     event_loop::EventLoop,
 };
 use winit_input_helper::WinitInputHelper;
 
-mod raycaster;
-mod vector;
+use twoderaycaster::{
+    debug, headless, load_map, open_borders, Direction, Editor, Error, Framebuffer, Gait, Map,
+    Minimap, Pick, Projection, RayCaster, Sprite, Vector,
+};
+
+use cli::Options;
+
+mod cli;
 mod window;
 
-pub const WIDTH: u32 = 1920;
-pub const HEIGHT: u32 = 1080;
-pub const SCALEFACTOR: u32 = 1;
+// window size, and the resolution drawn at unless `--resolution` says otherwise
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+
+// how many of the cast rays the ray view draws
+const RAYS_SHOWN: usize = 40;
+
+// the simulation always advances in steps of this many seconds
+const TICK: f64 = 1. / 120.;
+
+// longest frame the simulation catches up on, so a stall doesn't fast-forward the game
+const MAX_FRAME_TIME: f64 = 0.25;
+
+fn main() {
+    if let Err(e) = run() {
+        exit_with(e.as_ref());
+    }
+}
 
-pub static mut ACCELERATION: f64 = 0.1;
+fn exit_with(e: &dyn std::error::Error) -> ! {
+    eprintln!("error: {}", e);
+    std::process::exit(1);
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = Options::parse(&args)?;
+
+    if options.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    let (mut raycaster, map) = build_raycaster(&options)?;
+    let resolution = options.resolution;
+
+    // kept while hidden, so it comes back zoomed and turned the same way
+    let mut minimap = Minimap::default();
+    if options.minimap {
+        raycaster.set_minimap(Some(minimap));
+    }
+
+    // draw one frame and exit
+    if let Some(path) = &options.render {
+        let (width, height) = resolution.unwrap_or((WIDTH, HEIGHT));
+        headless::render_to_png(&raycaster, options.map_view, width, height, path)?;
+        return Ok(());
+    }
 
-fn main() -> Result<(), Error> {
     let mut input = WinitInputHelper::new();
 
     let event_loop = EventLoop::new();
-    let mut gw = window::GameWindow::new("Game", &event_loop)?;
-    let mut raycaster = raycaster::RayCaster::new(60.);
+    let mut gw = window::GameWindow::new(
+        "Game",
+        &event_loop,
+        (WIDTH, HEIGHT),
+        resolution,
+        options.vsync,
+    )?;
+    let (width, height) = resolution.unwrap_or_else(|| gw.window.inner_size().into());
+    let mut framebuffer = Framebuffer::new(width, height);
     let mut map_toggle = false;
+    let mut rays = false;
+    let mut fisheye = false;
+
+    // edits are saved as a text map next to the one loaded, which keeps everything
+    let mut editor = Editor::new(&raycaster, map);
+    let mut editing = false;
+    let save_path = Path::new(&options.map_path())
+        .with_extension("txt")
+        .to_string_lossy()
+        .into_owned();
+
+    let mut last_frame = Instant::now();
+    let mut accumulator = 0.;
+    let mut fps_timer = Instant::now();
+    let mut frames = 0;
 
     event_loop.run(move |event, _, control_flow| {
         match event {
             Event::RedrawRequested(_) => {
-                let now = std::time::Instant::now();
                 // println!("Redraw requested");
-                let frame = gw.pixels.frame_mut();
+                let now = Instant::now();
+                accumulator += (now - last_frame).as_secs_f64().min(MAX_FRAME_TIME);
+                last_frame = now;
 
-                // Clear the frame
-                for pixel in frame.chunks_exact_mut(4) {
-                    pixel.copy_from_slice(&[0, 0, 0, 0]); // Set every pixel to black
+                while accumulator >= TICK {
+                    raycaster.update_player(TICK);
+                    raycaster.update_doors(TICK);
+                    accumulator -= TICK;
                 }
+                raycaster.clear_input();
 
-                raycaster.update_player();
+                // draw partway between the last two ticks so motion stays smooth
+                raycaster.set_interpolation(accumulator / TICK);
 
-                raycaster.draw(frame, map_toggle).unwrap();
-                gw.pixels.render().unwrap();
-                let elapsed = now.elapsed().as_millis();
-                println!("FPS: {}", 1000 / elapsed)
+                framebuffer.clear();
+                let drawn = if editing {
+                    editor.draw(&raycaster, &mut framebuffer);
+                    Ok(())
+                } else if rays {
+                    let every = (framebuffer.width() as usize / RAYS_SHOWN).max(1);
+                    debug::draw_rays(&raycaster, &mut framebuffer, every);
+                    Ok(())
+                } else {
+                    raycaster.draw(&mut framebuffer, map_toggle)
+                };
+                let drawn = drawn.and_then(|()| {
+                    gw.pixels.frame_mut().copy_from_slice(framebuffer.pixels());
+                    gw.pixels.render().map_err(Error::from)
+                });
+                if let Err(e) = drawn {
+                    exit_with(&e);
+                }
+
+                frames += 1;
+                if fps_timer.elapsed().as_secs_f64() >= 1. {
+                    println!("FPS: {}", frames);
+                    frames = 0;
+                    fps_timer = Instant::now();
+                }
             }
 
             Event::WindowEvent {
@@ -56,131 +152,230 @@ fn main() -> Result<(), Error> {
                 ..
             } => {
                 println!("Window resized to {:?}", size);
-                gw.resize((size.width, size.height));
+
+                // a minimized window has no area to draw into
+                if size.width > 0 && size.height > 0 {
+                    let mut resized = gw.resize((size.width, size.height));
+
+                    // without a fixed resolution, draw at the window's new size
+                    if resolution.is_none() {
+                        resized =
+                            resized.and_then(|()| gw.resize_buffer((size.width, size.height)));
+                        framebuffer.resize(size.width, size.height);
+                    }
+
+                    if let Err(e) = resized {
+                        exit_with(&e);
+                    }
+                }
             }
 
             Event::DeviceEvent {
                 event: DeviceEvent::MouseMotion { delta },
                 ..
-            } => raycaster.change_direction(raycaster::Direction::Mouse(delta.0, delta.1)),
+            } if !editing => raycaster.change_direction(Direction::Mouse(delta.0, delta.1)),
 
             _ => {}
         }
 
         if input.update(&event) {
-            if input.held_shift() {
-                unsafe {
-                    ACCELERATION = 0.5;
-                }
+            let gait = if input.held_shift() {
+                Gait::Sprint
+            } else if input.held_control() {
+                Gait::Crouch
             } else {
-                unsafe {
-                    ACCELERATION = 0.1;
+                Gait::Walk
+            };
+            raycaster.movement_mut().set_gait(gait);
+
+            if input.key_pressed(VirtualKeyCode::E) {
+                editing = !editing;
+                editor.end_stroke();
+                if let Err(e) = gw.capture_cursor(!editing) {
+                    exit_with(&e);
                 }
             }
 
+            if editing {
+                let frame_size = (framebuffer.width(), framebuffer.height());
+                edit(
+                    &input,
+                    &gw,
+                    frame_size,
+                    &mut editor,
+                    &mut raycaster,
+                    &save_path,
+                );
+                gw.window.request_redraw();
+                return;
+            }
+
             if input.key_held(VirtualKeyCode::W) {
-                raycaster.change_direction(raycaster::Direction::Up)
+                raycaster.change_direction(Direction::Up)
             }
 
             if input.key_held(VirtualKeyCode::S) {
-                raycaster.change_direction(raycaster::Direction::Down)
+                raycaster.change_direction(Direction::Down)
             }
 
             if input.key_held(VirtualKeyCode::A) {
-                raycaster.change_direction(raycaster::Direction::Left)
+                raycaster.change_direction(Direction::Left)
             }
 
             if input.key_held(VirtualKeyCode::D) {
-                raycaster.change_direction(raycaster::Direction::Right)
+                raycaster.change_direction(Direction::Right)
+            }
+
+            if input.key_pressed(VirtualKeyCode::F) {
+                raycaster.interact();
             }
 
             if input.key_pressed(VirtualKeyCode::M) {
                 map_toggle = !map_toggle;
             }
+
+            if input.key_pressed(VirtualKeyCode::T) {
+                rays = !rays;
+            }
+
+            if input.key_pressed(VirtualKeyCode::N) {
+                let shown = raycaster.minimap().is_some();
+                raycaster.set_minimap(if shown { None } else { Some(minimap) });
+            }
+
+            if raycaster.minimap().is_some() {
+                if input.key_pressed(VirtualKeyCode::R) {
+                    minimap.rotate = !minimap.rotate;
+                }
+                if input.key_pressed(VirtualKeyCode::Equals) {
+                    minimap.cells = (minimap.cells / 1.5).max(4.);
+                }
+                if input.key_pressed(VirtualKeyCode::Minus) {
+                    minimap.cells = (minimap.cells * 1.5).min(128.);
+                }
+                raycaster.set_minimap(Some(minimap));
+            }
+
+            if input.key_pressed(VirtualKeyCode::P) {
+                fisheye = !fisheye;
+                raycaster.set_projection(if fisheye {
+                    Projection::Fisheye
+                } else {
+                    Projection::Planar
+                });
+            }
         }
 
         gw.window.request_redraw();
     });
 }
 
-fn verline(frame: &mut [u8], x: usize, y1: usize, y2: usize, rgba: [u8; 4], scale: usize) {
-    for y in (y1 * scale)..=(y2 * scale) {
-        set_pixel(frame, x, y, rgba, scale);
-    }
-}
-
-pub fn line(
-    frame: &mut [u8],
-    x1: isize,
-    y1: isize,
-    x2: isize,
-    y2: isize,
-    color: [u8; 4],
-    scale: usize,
+// the map editor's controls, with the cursor over the frame
+fn edit(
+    input: &WinitInputHelper,
+    gw: &window::GameWindow,
+    frame_size: (u32, u32),
+    editor: &mut Editor,
+    raycaster: &mut RayCaster,
+    save_path: &str,
 ) {
-    if x1 == x2 {
-        verline(frame, x1 as usize, y1 as usize, y2 as usize, color, scale);
-        return;
-    }
-    let dx = isize::abs(x2 - x1) * scale as isize;
-    let sx = if x1 < x2 { 1 } else { -1 };
-    let dy = -isize::abs(y2 - y1) * scale as isize;
-    let sy = if y1 < y2 { 1 } else { -1 };
-    let mut err = dx + dy;
-    let mut x = x1 * scale as isize;
-    let mut y = y1 * scale as isize;
-
-    loop {
-        set_pixel(frame, x as usize, y as usize, color, scale);
-
-        if x == x2 * scale as isize && y == y2 * scale as isize {
-            break;
+    let pick = input
+        .mouse()
+        .and_then(|pos| gw.pixels.window_pos_to_pixel(pos).ok())
+        .and_then(|(x, y)| editor.pick(raycaster, frame_size, x, y));
+    editor.hover(pick);
+
+    match pick {
+        Some(Pick::Swatch(i)) if input.mouse_pressed(0) => editor.select(i),
+        Some(Pick::Cell(x, y))
+            if input.mouse_pressed(2) || (input.mouse_pressed(0) && input.held_shift()) =>
+        {
+            if let Err(e) = editor.move_spawn(raycaster, x, y) {
+                eprintln!("error: {}", e);
+            }
         }
+        Some(Pick::Cell(x, y)) if input.mouse_held(0) && !input.held_shift() => {
+            editor.paint(raycaster, x, y);
+        }
+        Some(Pick::Cell(x, y)) if input.mouse_held(1) => {
+            editor.erase(raycaster, x, y);
+        }
+        _ => {}
+    }
+    if input.mouse_released(0) || input.mouse_released(1) {
+        editor.end_stroke();
+    }
 
-        let e2 = 2 * err;
+    let scroll = input.scroll_diff();
+    if scroll != 0. {
+        editor.cycle(-scroll.signum() as isize);
+    }
+    if input.key_pressed(VirtualKeyCode::Tab) {
+        editor.cycle(1);
+    }
+    if input.key_pressed(VirtualKeyCode::RBracket) {
+        editor.adjust_height(1);
+    }
+    if input.key_pressed(VirtualKeyCode::LBracket) {
+        editor.adjust_height(-1);
+    }
 
-        if e2 >= dy {
-            err += dy;
-            x += sx * scale as isize;
+    if input.held_control() {
+        let redo = input.key_pressed(VirtualKeyCode::Y)
+            || (input.key_pressed(VirtualKeyCode::Z) && input.held_shift());
+        if redo {
+            editor.redo(raycaster);
+        } else if input.key_pressed(VirtualKeyCode::Z) {
+            editor.undo(raycaster);
         }
 
-        if e2 <= dx {
-            err += dx;
-            y += sy * scale as isize;
+        if input.key_pressed(VirtualKeyCode::S) {
+            match editor.save(raycaster, save_path) {
+                Ok(()) => println!("Saved the map to {}", save_path),
+                Err(e) => eprintln!("error: {}", e),
+            }
         }
     }
 }
 
-fn filled_rectangle(
-    frame: &mut [u8],
-    x1: usize,
-    y1: usize,
-    x2: usize,
-    y2: usize,
-    color: [u8; 4],
-    scale: usize,
-) {
-    for x in (x1 * scale)..=(x2 * scale) {
-        for y in (y1 * scale)..=(y2 * scale) {
-            if x >= WIDTH as usize || y >= HEIGHT as usize {
-                continue;
-            }
-            set_pixel(frame, x, y, color, scale);
-        }
+// the raycaster, and everything else the map came with
+fn build_raycaster(options: &Options) -> Result<(RayCaster, Map), Box<dyn std::error::Error>> {
+    let path = options.map_path();
+    let mut map = load_map(&path)?;
+
+    let open = open_borders(&map.cells);
+    if let Some((x, y)) = open.first() {
+        eprintln!(
+            "warning: {} is not enclosed, {} empty cells touch the edge (the first at {}, {})",
+            path,
+            open.len(),
+            x,
+            y
+        );
     }
-}
 
-pub fn set_pixel(frame: &mut [u8], x: usize, y: usize, color: [u8; 4], scale: usize) {
-    for i in 0..scale {
-        for j in 0..scale {
-            let xi = x * scale + i;
-            let yj = y * scale + j;
-            if xi < WIDTH as usize && yj < HEIGHT as usize {
-                let index = (yj * WIDTH as usize + xi) * 4;
-                if index + 4 <= frame.len() {
-                    frame[index..index + 4].copy_from_slice(&color);
-                }
-            }
-        }
+    let mut raycaster = RayCaster::with_map(std::mem::take(&mut map.cells), options.fov)?;
+
+    for &sprite in &map.sprites {
+        raycaster.add_sprite(sprite);
     }
+
+    // the sprites are placed for the bundled map
+    if options.map.is_none() {
+        raycaster.add_sprite(Sprite::new(Vector::new(17.5, 12.5), 0));
+        raycaster.add_sprite(Sprite::new(Vector::new(14.5, 10.5), 1));
+        raycaster.add_sprite(Sprite::new(Vector::new(14.5, 14.5), 2));
+    }
+
+    // --spawn wins over the map's own spawn, which wins over the default
+    let (pos, dir) = match options.spawn.or(map.spawn) {
+        Some((pos, dir)) => (pos, dir.unwrap_or_else(|| raycaster.direction())),
+        None => (raycaster.position(), raycaster.direction()),
+    };
+    raycaster
+        .set_spawn(pos, dir)
+        .map_err(|e| format!("{}, pick another with --spawn X,Y", e))?;
+    raycaster.set_camera(pos, dir, options.pitch);
+
+    Ok((raycaster, map))
 }
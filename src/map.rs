@@ -0,0 +1,316 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::{Error, Result},
+    sprite::Sprite,
+    textmap::{load_text_map, save_text_map},
+    tiled::load_tiled,
+    vector::Vector,
+};
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub struct MapCell {
+    pub color: [u8; 4],
+    pub solid: MapCellType,
+    pub height: f64,
+    pub texture: Option<usize>,
+    pub floor: Option<Surface>,
+    pub ceiling: Option<Surface>,
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Surface {
+    Color([u8; 4]),
+    Texture(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum MapCellType {
+    Empty,
+    Wall,
+    // a panel across the middle of its cell that slides aside into the wall next to it
+    Door,
+    // a wall that slides back through its cell when pushed, leaving it open
+    PushWall,
+}
+
+// a grid of cells plus whatever else the file it came from says about it; formats
+// without metadata, like images, leave the rest empty
+#[derive(Clone, PartialEq, Debug)]
+pub struct Map {
+    pub cells: Vec<Vec<MapCell>>,
+    pub name: Option<String>,
+    // where the player starts, and optionally which way they face
+    pub spawn: Option<(Vector<f64>, Option<Vector<f64>>)>,
+    // named points for gameplay code, in file order
+    pub markers: Vec<(String, Vector<f64>)>,
+    pub sprites: Vec<Sprite>,
+}
+
+impl Map {
+    pub fn new(cells: Vec<Vec<MapCell>>) -> Self {
+        Self {
+            cells,
+            name: None,
+            spawn: None,
+            markers: Vec::new(),
+            sprites: Vec::new(),
+        }
+    }
+
+    pub fn marker(&self, name: &str) -> Option<Vector<f64>> {
+        self.markers
+            .iter()
+            .find(|(marker, _)| marker == name)
+            .map(|&(_, pos)| pos)
+    }
+}
+
+impl MapCell {
+    pub fn new(color: [u8; 4], solid: MapCellType, height: f64) -> Self {
+        Self {
+            color,
+            solid,
+            height,
+            texture: None,
+            floor: None,
+            ceiling: None,
+        }
+    }
+
+    pub fn with_texture(mut self, texture: usize) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            color: [0, 0, 0, 0],
+            solid: MapCellType::Empty,
+            height: 0.0,
+            texture: None,
+            floor: None,
+            ceiling: None,
+        }
+    }
+}
+
+// loads a map in whichever format the extension says: `.txt` for the text format,
+// `.tmx`, `.tmj` or `.json` for Tiled, and an image otherwise
+pub fn load_map(path: &str) -> Result<Map> {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("txt") => load_text_map(path),
+        Some("tmx" | "tmj" | "json") => load_tiled(path),
+        _ => generate_map(path).map(Map::new),
+    }
+}
+
+pub fn generate_map(path: &str) -> Result<Vec<Vec<MapCell>>> {
+    let img = image::open(path).map_err(|e| Error::image(path, e))?;
+    let img = img.to_rgba8();
+    let (width, height) = img.dimensions();
+
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidMap {
+            path: path.into(),
+            reason: "the image is empty".to_string(),
+        });
+    }
+
+    let mut buffer: Vec<Vec<MapCell>> =
+        vec![vec![MapCell::empty(); width as usize]; height as usize];
+
+    // each distinct wall color gets the next texture in the atlas
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y).0;
+            let (solid, height) = if pixel[3] == 0 {
+                (MapCellType::Empty, 0.)
+            } else {
+                // alpha sets the height, so translucent pixels make shorter walls
+                (MapCellType::Wall, pixel[3] as f64 / 255.)
+            };
+            let pixel = [
+                pixel[0],
+                pixel[1],
+                pixel[2],
+                if solid == MapCellType::Empty { 0 } else { 255 },
+            ];
+            let mut cell = MapCell::new(pixel, solid, height);
+
+            if solid == MapCellType::Wall {
+                let texture = palette.iter().position(|&c| c == pixel).unwrap_or_else(|| {
+                    palette.push(pixel);
+                    palette.len() - 1
+                });
+                cell = cell.with_texture(texture);
+            }
+
+            buffer[y as usize][x as usize] = cell;
+        }
+    }
+
+    // a companion heightmap overrides the alpha heights and allows walls taller than
+    // one unit
+    let path = Path::new(path);
+    let heightmap = heightmap_path(path);
+    if heightmap.exists() {
+        apply_heightmap(&mut buffer, &heightmap)?;
+    }
+
+    validate(path, &buffer)?;
+    Ok(buffer)
+}
+
+// saves a map in whichever format the extension says, `.txt` for the text format
+// and PNG otherwise, which only keeps the cells
+pub fn save_map(map: &Map, path: &str) -> Result<()> {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("txt") => save_text_map(map, path),
+        _ => save_map_png(&map.cells, path),
+    }
+}
+
+// the other way from generate_map: walls become opaque pixels of their color, with
+// their height in the alpha channel if it fits there and in a companion heightmap if
+// not. Textures come back from the colors on loading, floors and ceilings aren't kept,
+// and short rows are padded with empty cells
+pub fn save_map_png(map: &[Vec<MapCell>], path: &str) -> Result<()> {
+    let (width, height) = (map.iter().map(Vec::len).max().unwrap_or(0), map.len());
+    let cells = || {
+        map.iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, cell)| (x, y, cell)))
+    };
+
+    // alpha holds heights from 1/255 to 1 in steps of 1/255
+    let alpha = |height: f64| {
+        let alpha = (height * 255.).round();
+        ((alpha - height * 255.).abs() < 1e-6 && (1. ..=255.).contains(&alpha))
+            .then_some(alpha as u8)
+    };
+    let in_alpha = cells()
+        .filter(|(_, _, cell)| cell.solid != MapCellType::Empty)
+        .all(|(_, _, cell)| alpha(cell.height).is_some());
+
+    let mut img = image::RgbaImage::new(width as u32, height as u32);
+    let mut heights = (!in_alpha).then(|| {
+        image::RgbaImage::from_pixel(width as u32, height as u32, image::Rgba([0, 0, 0, 255]))
+    });
+
+    for (x, y, cell) in cells() {
+        let [r, g, b, _] = cell.color;
+        let a = match cell.solid {
+            MapCellType::Empty => 0,
+            _ => alpha(cell.height).filter(|_| in_alpha).unwrap_or(255),
+        };
+        img.put_pixel(x as u32, y as u32, image::Rgba([r, g, b, a]));
+
+        // doors and push walls go in as walls, so they need a height too
+        if let Some(heights) = heights
+            .as_mut()
+            .filter(|_| cell.solid != MapCellType::Empty)
+        {
+            let red = (cell.height * 64.).round();
+            if !(0. ..=255.).contains(&red) {
+                return Err(Error::InvalidMap {
+                    path: path.into(),
+                    reason: format!(
+                        "the wall at ({}, {}) is {} high, a heightmap only goes up to {}",
+                        x,
+                        y,
+                        cell.height,
+                        255. / 64.
+                    ),
+                });
+            }
+            let red = red as u8;
+            heights.put_pixel(x as u32, y as u32, image::Rgba([red, red, red, 255]));
+        }
+    }
+
+    img.save_with_format(path, image::ImageFormat::Png)
+        .map_err(|e| Error::image(path, e))?;
+
+    let heightmap = heightmap_path(Path::new(path));
+    match heights {
+        Some(heights) => heights
+            .save_with_format(&heightmap, image::ImageFormat::Png)
+            .map_err(|e| Error::image(&heightmap, e)),
+        // one left over from before would override the heights just saved
+        None if heightmap.exists() => {
+            std::fs::remove_file(&heightmap).map_err(|source| Error::Io {
+                path: heightmap,
+                source,
+            })
+        }
+        None => Ok(()),
+    }
+}
+
+// map.png -> map_height.png
+fn heightmap_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}_height.png", stem))
+}
+
+// checks every loader runs on the grid it produced; open borders are left for the
+// caller to warn about, see open_borders
+pub(crate) fn validate(path: &Path, map: &[Vec<MapCell>]) -> Result<()> {
+    if map.iter().all(|row| row.is_empty()) {
+        return Err(Error::InvalidMap {
+            path: path.into(),
+            reason: "it has no cells".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// empty cells next to the edge of the map, or to the end of a shorter row, which rays
+// and players could leave the map through
+pub fn open_borders(map: &[Vec<MapCell>]) -> Vec<(usize, usize)> {
+    let inside = |x: usize, y: Option<usize>| {
+        y.and_then(|y| map.get(y))
+            .is_some_and(|row: &Vec<MapCell>| x < row.len())
+    };
+
+    let mut open = Vec::new();
+    for (y, row) in map.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if cell.solid != MapCellType::Empty {
+                continue;
+            }
+
+            let edge = x == 0
+                || x + 1 == row.len()
+                || !inside(x, y.checked_sub(1))
+                || !inside(x, Some(y + 1));
+            if edge {
+                open.push((x, y));
+            }
+        }
+    }
+    open
+}
+
+// heightmap red channel is wall height in 64ths of a unit, so 64 is a full wall
+fn apply_heightmap(map: &mut [Vec<MapCell>], path: &Path) -> Result<()> {
+    let img = image::open(path)
+        .map_err(|e| Error::image(path, e))?
+        .to_rgba8();
+
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if let Some(cell) = map
+            .get_mut(y as usize)
+            .and_then(|row| row.get_mut(x as usize))
+        {
+            if cell.solid != MapCellType::Empty {
+                cell.height = pixel.0[0] as f64 / 64.;
+            }
+        }
+    }
+
+    Ok(())
+}
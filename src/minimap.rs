@@ -0,0 +1,117 @@
+use crate::{
+    framebuffer::Framebuffer,
+    map::{MapCell, MapCellType},
+    vector::Vector,
+};
+
+// floor inside the map, and everything past its edge
+const FLOOR: [u8; 4] = [30, 30, 36, 255];
+const OUTSIDE: [u8; 4] = [0, 0, 0, 255];
+
+// the part of the map the last frame's rays reached
+const CONE: [u8; 4] = [255, 220, 120, 255];
+const CONE_OPACITY: f64 = 0.35;
+
+const PLAYER: [u8; 4] = [255, 40, 40, 255];
+const BORDER: [u8; 4] = [200, 200, 200, 255];
+
+// a small map drawn over the top right corner of the 3D view, centred on the player
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Minimap {
+    // side length, as a fraction of the frame height
+    pub size: f64,
+    // how many cells fit across it, so fewer zooms in
+    pub cells: f64,
+    // turn the map with the player so forward is always up, instead of north
+    pub rotate: bool,
+    // 1 hides the scene underneath, 0 is invisible
+    pub opacity: f64,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            size: 0.3,
+            cells: 16.,
+            rotate: false,
+            opacity: 0.75,
+        }
+    }
+}
+
+impl Minimap {
+    // pos and dir are the eye's, cell looks up the map and seen says whether the
+    // frame's rays reached a point before hitting something
+    pub(crate) fn draw(
+        &self,
+        frame: &mut Framebuffer,
+        pos: Vector<f64>,
+        dir: Vector<f64>,
+        cell: impl Fn(i32, i32) -> Option<MapCell>,
+        seen: impl Fn(Vector<f64>) -> bool,
+    ) {
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let size = ((height as f64 * self.size) as usize)
+            .min(width)
+            .min(height);
+        if size < 2 || self.cells <= 0. {
+            return;
+        }
+        let margin = (height / 50).min(width - size).min(height - size);
+        let (left, top) = (width - size - margin, margin);
+
+        // map units per pixel along the screen's axes
+        let scale = self.cells / size as f64;
+        let (right, down) = if self.rotate {
+            let forward = dir * (1. / dir.length());
+            (Vector::new(-forward.y, forward.x), forward * -1.)
+        } else {
+            (Vector::new(1., 0.), Vector::new(0., 1.))
+        };
+
+        let centre = size as f64 / 2.;
+        let dot = (size / 40).max(1) as f64;
+        let opacity = self.opacity.clamp(0., 1.);
+        let pixels = frame.pixels_mut();
+
+        for y in 0..size {
+            for x in 0..size {
+                let (dx, dy) = (x as f64 + 0.5 - centre, y as f64 + 0.5 - centre);
+                let point = pos + right * (dx * scale) + down * (dy * scale);
+
+                let mut color = match cell(point.x.floor() as i32, point.y.floor() as i32) {
+                    Some(cell) if cell.solid != MapCellType::Empty => {
+                        [cell.color[0], cell.color[1], cell.color[2], 255]
+                    }
+                    Some(_) => FLOOR,
+                    None => OUTSIDE,
+                };
+                if seen(point) {
+                    color = blend(color, CONE, CONE_OPACITY);
+                }
+
+                let edge = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+                if edge {
+                    color = BORDER;
+                } else if dx.abs() <= dot && dy.abs() <= dot {
+                    color = PLAYER;
+                }
+
+                let i = ((top + y) * width + left + x) * 4;
+                let under = [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]];
+                pixels[i..i + 4].copy_from_slice(&blend(under, color, opacity));
+            }
+        }
+    }
+}
+
+// over laid on top of under, keeping under's alpha
+fn blend(under: [u8; 4], over: [u8; 4], opacity: f64) -> [u8; 4] {
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * opacity).round() as u8;
+    [
+        mix(under[0], over[0]),
+        mix(under[1], over[1]),
+        mix(under[2], over[2]),
+        under[3],
+    ]
+}
@@ -0,0 +1,77 @@
+use crate::vector::Vector;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Gait {
+    Walk,
+    Sprint,
+    Crouch,
+}
+
+// how the player speeds up and slows down, so each RayCaster can feel different
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MovementController {
+    pub walk_speed: f64,   // units per second
+    pub sprint_speed: f64, // units per second
+    pub crouch_speed: f64, // units per second
+    pub acceleration: f64, // units per second squared, while getting up to speed
+    pub deceleration: f64, // units per second squared, while stopping or slowing down
+    pub radius: f64,       // units, how close the player gets to walls
+    gait: Gait,
+}
+
+impl Default for MovementController {
+    fn default() -> Self {
+        Self {
+            walk_speed: 25.,
+            sprint_speed: 60.,
+            crouch_speed: 10.,
+            acceleration: 200.,
+            deceleration: 150.,
+            radius: 0.25,
+            gait: Gait::Walk,
+        }
+    }
+}
+
+impl MovementController {
+    pub fn gait(&self) -> Gait {
+        self.gait
+    }
+
+    pub fn set_gait(&mut self, gait: Gait) {
+        self.gait = gait;
+    }
+
+    pub fn max_speed(&self) -> f64 {
+        match self.gait {
+            Gait::Walk => self.walk_speed,
+            Gait::Sprint => self.sprint_speed,
+            Gait::Crouch => self.crouch_speed,
+        }
+    }
+
+    // velocity after dt seconds of steering towards wish at the gait's speed; it only
+    // goes over max_speed while still slowing down from a faster gait
+    pub fn accelerate(&self, vel: Vector<f64>, wish: Vector<f64>, dt: f64) -> Vector<f64> {
+        let wish_length = wish.length();
+        let target = if wish_length > 0. {
+            wish * (self.max_speed() / wish_length)
+        } else {
+            Vector::new(0., 0.)
+        };
+
+        let rate = if wish_length > 0. && vel.length() <= self.max_speed() {
+            self.acceleration
+        } else {
+            self.deceleration
+        };
+
+        let delta = target - vel;
+        let step = rate * dt;
+        if delta.length() <= step {
+            target
+        } else {
+            vel + delta * (step / delta.length())
+        }
+    }
+}
@@ -1,9 +1,62 @@
-use crate::{line, set_pixel, vector::Vector, verline, ACCELERATION, HEIGHT, WIDTH};
+use std::{collections::HashMap, f64::consts::PI};
+
+use crate::{
+    assets_dir,
+    collision::{move_circle, touches},
+    columns::{Column, ColumnBuffer},
+    draw::{line, set_pixel},
+    error::{Error, Result},
+    framebuffer::Framebuffer,
+    map::{generate_map, MapCell, MapCellType, Surface},
+    minimap::Minimap,
+    movement::MovementController,
+    sprite::Sprite,
+    texture::{load_atlas, Texture},
+    vector::Vector,
+};
 
 pub struct RayCaster {
     player: Player,
     map: Vec<Vec<MapCell>>,
+    textures: Vec<Texture>,
+    sprites: Vec<Sprite>,
+    sprite_textures: Vec<Texture>,
+    max_height: f64,
+    floor: Surface,
+    ceiling: Surface,
     fov: f64,
+    projection: Projection,
+    interpolation: f64,
+    threads: usize,
+    max_distance: f64,
+    void: [u8; 4],
+    minimap: Option<Minimap>,
+    // doors and push walls that have been opened; the rest are closed
+    doors: HashMap<(i32, i32), Door>,
+}
+
+// how far ahead of the player interact finds a door
+const REACH: f64 = 1.5;
+
+// how much of the way open they get each second
+const DOOR_SPEED: f64 = 1.;
+const PUSH_WALL_SPEED: f64 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Door {
+    // 0 closed to 1 open
+    open: f64,
+    opening: bool,
+    // which way along the grid it moves as it opens
+    toward: Vector<i32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projection {
+    // rays spread across a flat camera plane, keeping walls straight
+    Planar,
+    // rays spread at even angles with raw distances, bowing walls outward
+    Fisheye,
 }
 
 struct Ray {
@@ -11,11 +64,84 @@ struct Ray {
     hit: bool,
 }
 
+// what every column of a frame shares
+struct View {
+    pos: Vector<f64>,
+    width: usize,
+    height: usize,
+    focal_length: f64,
+    horizon: f64,
+    row_distance: Vec<f64>,
+    // keep every cell the rays step through, which drawing doesn't need
+    visits: bool,
+}
+
+// a wall drawn in a column, hiding anything farther away from its top down
+#[derive(Clone, PartialEq, Debug)]
+struct Occluder {
+    distance: f64,
+    top: usize,
+}
+
+// which kind of grid line a ray crossed into a wall
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Side {
+    // a vertical one, so the face looks east or west
+    X,
+    // a horizontal one, so the face looks north or south
+    Y,
+}
+
+// a wall a ray ran into
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hit {
+    pub cell: Vector<i32>,
+    pub wall: MapCell,
+    pub side: Side,
+    // in lengths of the ray's direction: perpendicular to the camera plane for a frame's
+    // rays (straight-line for fisheye), and straight-line from cast
+    pub distance: f64,
+    pub point: Vector<f64>,
+    // where along the face, 0 to 1, which is the texture's x coordinate
+    pub u: f64,
+}
+
+// what one column's ray went through, nearest first
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trace {
+    pub origin: Vector<f64>,
+    pub dir: Vector<f64>,
+    // walls it hit, including short ones it carried on over and ones nearer walls hide
+    pub hits: Vec<Hit>,
+    // every cell it stepped into, only kept by RayCaster::trace
+    pub visited: Vec<Vector<i32>>,
+    // how far it got before a wall stopped it, it left the map or reached max_distance
+    pub reach: f64,
+    occluders: Vec<Occluder>,
+}
+
+// steps a ray across the grid one cell at a time, the same way for drawing and casting
+struct Dda {
+    pos: Vector<f64>,
+    dir: Vector<f64>,
+    // the cell the ray is in
+    map_pos: Vector<i32>,
+    // how far the ray goes to cross one cell along each axis
+    delta_dist: Vector<f64>,
+    // which way map_pos moves along each axis
+    step: Vector<i32>,
+    // how far the ray has gone at the next crossing along each axis
+    side_dist: Vector<f64>,
+}
+
 struct Player {
     pub pos: Vector<f64>,
+    pub prev_pos: Vector<f64>, // pos before the last tick, for interpolating
     pub dir: Vector<f64>,
-    pub vel: Vector<f64>,
-    pub pitch: f64, // -1.0 to 1.0
+    pub vel: Vector<f64>,  // units per second
+    pub wish: Vector<f64>, // movement keys held this frame
+    pub pitch: f64,        // -1.0 to 1.0
+    pub movement: MovementController,
 }
 
 pub enum Direction {
@@ -26,48 +152,40 @@ pub enum Direction {
     Mouse(f64, f64),
 }
 
-#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
-struct MapCell {
-    pub color: [u8; 4],
-    pub solid: MapCellType,
-    pub height: f64,
-}
-
-#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
-enum MapCellType {
-    Empty,
-    Wall,
-}
-
-impl MapCell {
-    pub fn new(color: [u8; 4], solid: MapCellType, height: f64) -> Self {
-        Self {
-            color,
-            solid,
-            height,
-        }
+impl RayCaster {
+    pub fn new(fov: f64) -> Result<Self> {
+        Self::with_map(generate_map(&format!("{}/map.png", assets_dir()))?, fov)
     }
 
-    pub fn empty() -> Self {
-        Self {
-            color: [0, 0, 0, 0],
-            solid: MapCellType::Empty,
-            height: 0.0,
-        }
+    pub fn with_map(map: Vec<Vec<MapCell>>, fov: f64) -> Result<Self> {
+        Self::with_assets(map, fov, &assets_dir())
     }
-}
 
-impl RayCaster {
-    pub fn new(fov: f64) -> Self {
-        Self {
+    // like with_map, with the texture and sprite atlases read from another directory
+    pub fn with_assets(map: Vec<Vec<MapCell>>, fov: f64, assets: &str) -> Result<Self> {
+        let max_height = map
+            .iter()
+            .flatten()
+            .fold(0., |max, cell| cell.height.max(max));
+
+        Ok(Self {
             player: Player {
                 pos: Vector { x: 22.0, y: 12.0 },
+                prev_pos: Vector { x: 22.0, y: 12.0 },
                 dir: Vector { x: -1.0, y: 0.0 },
                 vel: Vector { x: 0., y: 0. },
+                wish: Vector { x: 0., y: 0. },
                 pitch: 0.5,
+                movement: MovementController::default(),
             },
 
-            map: generate_map(),
+            map,
+            textures: load_atlas(&format!("{}/textures.png", assets))?,
+            sprites: Vec::new(),
+            sprite_textures: load_atlas(&format!("{}/sprites.png", assets))?,
+            max_height,
+            floor: Surface::Texture(7),
+            ceiling: Surface::Color([40, 40, 48, 255]),
 
             // map: [
             //     [1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1],
@@ -96,17 +214,26 @@ impl RayCaster {
             //     [1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1]
             //   ],
             fov,
-        }
+            projection: Projection::Planar,
+            interpolation: 1.,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            max_distance: f64::INFINITY,
+            void: [0, 0, 0, 255],
+            minimap: None,
+            doors: HashMap::new(),
+        })
     }
 
-    pub fn draw(&self, frame: &mut [u8], map_toggle: bool) -> Result<(), String> {
+    pub fn draw(&self, frame: &mut Framebuffer, map_toggle: bool) -> Result<()> {
+        let pos = self.eye();
+
         if map_toggle {
             // map
             for y in 0..self.map.len() {
                 for x in 0..self.map[y].len() {
                     let cell = self.map[y][x];
 
-                    let dist = distance_squared(Vector::new(x as f64, y as f64), self.player.pos);
+                    let dist = distance_squared(Vector::new(x as f64, y as f64), pos);
 
                     // cell.color gets darker farther away from the player
                     let color = [
@@ -121,19 +248,13 @@ impl RayCaster {
                 }
             }
 
-            set_pixel(
-                frame,
-                self.player.pos.x as usize,
-                self.player.pos.y as usize,
-                [25, 0, 255, 255],
-                1,
-            );
+            set_pixel(frame, pos.x as usize, pos.y as usize, [25, 0, 255, 255], 1);
             line(
                 frame,
-                self.player.pos.x as isize,
-                self.player.pos.y as isize,
-                (self.player.pos.x + self.player.dir.x * 10.) as isize,
-                (self.player.pos.y + self.player.dir.y * 10.) as isize,
+                pos.x as isize,
+                pos.y as isize,
+                (pos.x + self.player.dir.x * 10.) as isize,
+                (pos.y + self.player.dir.y * 10.) as isize,
                 [255, 0, 0, 255],
                 1,
             );
@@ -161,152 +282,694 @@ impl RayCaster {
         }
 
         // raycasting
-        let half_fov: f64 = self.fov / 2.;
-        const NUMRAYS: f64 = WIDTH as f64;
-        for i in 0..NUMRAYS as usize {
-            let angle = (self.fov / NUMRAYS * i as f64 - half_fov) * 1f64.to_radians();
-            let mut ray = Ray {
-                dir: self.player.dir.rotate(angle),
-                hit: false,
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let view = self.view(width, height, false);
+
+        // everything is drawn column by column, then turned into rows once at the end
+        let mut target = ColumnBuffer::new(width, height);
+        let mut columns: Vec<Column> = target.columns_mut().collect();
+
+        // each thread gets a strip of neighbouring columns
+        let threads = self.threads.clamp(1, width);
+        let strip = width.div_ceil(threads);
+
+        let draw_strip = |first: usize, columns: &mut [Column]| -> Vec<Trace> {
+            columns
+                .iter_mut()
+                .enumerate()
+                .map(|(i, column)| self.draw_column(&view, first + i, Some(column)))
+                .collect()
+        };
+
+        // what each column's ray hit, for clipping sprites and lighting up the minimap
+        let traces: Vec<Trace> = if threads == 1 {
+            draw_strip(0, &mut columns)
+        } else {
+            let strips = std::thread::scope(|scope| {
+                let workers: Vec<_> = columns
+                    .chunks_mut(strip)
+                    .enumerate()
+                    .map(|(n, columns)| scope.spawn(move || draw_strip(n * strip, columns)))
+                    .collect();
+
+                // every worker is joined before giving up, or the scope would panic too
+                workers
+                    .into_iter()
+                    .map(|worker| worker.join())
+                    .collect::<Vec<_>>()
+            });
+
+            let mut traces = Vec::with_capacity(width);
+            for strip in strips {
+                traces.extend(
+                    strip.map_err(|_| Error::Render("a thread drawing columns panicked".into()))?,
+                );
+            }
+            traces
+        };
+
+        self.draw_sprites(&mut target, &view, &traces);
+        target.transpose_into(frame);
+
+        if let Some(minimap) = &self.minimap {
+            // lit wherever a column's ray got to before stopping
+            let seen = |point| match self.project(point, width) {
+                Some((column, distance)) if column >= 0. && (column as usize) < width => {
+                    distance <= traces[column as usize].reach
+                }
+                _ => false,
             };
+            minimap.draw(frame, pos, self.player.dir, |x, y| self.cell(x, y), seen);
+        }
 
-            // map_pos is the current map cell we are in
-            let mut map_pos: Vector<i32> = Vector::new(
-                self.player.pos.x.floor() as i32,
-                self.player.pos.y.floor() as i32,
-            );
+        Ok(())
+    }
 
-            // delta of ray to next map cell
-            let delta_dist = Vector {
-                x: (1.0 / ray.dir.x).abs(),
-                y: (1.0 / ray.dir.y).abs(),
+    // the rays a frame of this size casts, without drawing anything
+    pub fn trace(&self, width: usize, height: usize) -> Vec<Trace> {
+        let view = self.view(width, height, true);
+        (0..width)
+            .map(|i| self.draw_column(&view, i, None))
+            .collect()
+    }
+
+    // the first wall a ray from origin meets within max_distance, however short, for line
+    // of sight and hitscans; the cell origin is in doesn't count, and distance is in units
+    pub fn cast(&self, origin: Vector<f64>, dir: Vector<f64>, max_distance: f64) -> Option<Hit> {
+        let length = dir.length();
+        if length == 0. || !length.is_finite() {
+            return None;
+        }
+
+        let mut dda = Dda::new(origin, dir * (1. / length));
+        loop {
+            let (map_pos, side, distance) = dda.step();
+            if distance > max_distance {
+                return None;
+            }
+
+            // None once it leaves the map
+            let cell = self.cell(map_pos.x, map_pos.y)?;
+            if let Some(hit) = self.wall_hit(&dda, cell, side, distance) {
+                return Some(hit).filter(|hit| hit.distance <= max_distance);
+            }
+        }
+    }
+
+    // where a ray stepping into a cell meets what's in it: walls fill the cell, while
+    // doors and push walls only fill the part of it that isn't open
+    fn wall_hit(&self, dda: &Dda, wall: MapCell, side: Side, distance: f64) -> Option<Hit> {
+        let door = match wall.solid {
+            MapCellType::Empty => return None,
+            MapCellType::Wall => return Some(dda.hit(wall, side, distance, 0.)),
+            MapCellType::Door | MapCellType::PushWall => self.door(dda.map_pos.x, dda.map_pos.y),
+        };
+        if door.open >= 1. {
+            return None;
+        }
+
+        // what's left of the cell along the way it opens, and across it
+        let along = if door.toward.x + door.toward.y > 0 {
+            (door.open, 1.)
+        } else {
+            (0., 1. - door.open)
+        };
+        let (across, shift) = match wall.solid {
+            // a door is a thin panel that carries its texture aside with it
+            MapCellType::Door => ((0.5, 0.5), door.open),
+            _ => ((0., 1.), 0.),
+        };
+
+        let (lo, hi) = if door.toward.x != 0 {
+            (
+                Vector::new(along.0, across.0),
+                Vector::new(along.1, across.1),
+            )
+        } else {
+            (
+                Vector::new(across.0, along.0),
+                Vector::new(across.1, along.1),
+            )
+        };
+        dda.hit_box(wall, lo, hi, shift)
+    }
+
+    fn view(&self, width: usize, height: usize, visits: bool) -> View {
+        let focal_length = self.focal_length(width);
+
+        let shear = (self.player.pitch * height as f64 / 2.0) as usize;
+        let horizon = height as f64 / 2. + shear as f64;
+
+        View {
+            pos: self.eye(),
+            width,
+            height,
+            focal_length,
+            horizon,
+            // distance to the floor (below the horizon) or ceiling (above it) seen by each row
+            row_distance: (0..height)
+                .map(|y| focal_length / 2. / (y as f64 + 0.5 - horizon).abs())
+                .collect(),
+            visits,
+        }
+    }
+
+    // casts the ray for screen column i and draws it, if there's a column to draw in
+    fn draw_column(&self, view: &View, i: usize, mut column: Option<&mut Column>) -> Trace {
+        let pos = view.pos;
+        let horizon = view.horizon;
+        let fog = |distance: f64| fog(distance, self.player.pitch);
+
+        let mut ray = Ray {
+            dir: self.ray_dir(i, view.width),
+            hit: false,
+        };
+
+        let mut dda = Dda::new(pos, ray.dir);
+
+        // floor and ceiling for the rows in range
+        let fill = |column: &mut Column, rows: std::ops::Range<usize>| {
+            for y in rows {
+                let distance = view.row_distance[y];
+                let point = pos + ray.dir * distance;
+
+                // past the edge of the map, or farther than rays go, is the void
+                let color = match self.surface_color(point, (y as f64) < horizon) {
+                    Some(mut color) if distance <= self.max_distance => {
+                        color.mul_assign(fog(distance));
+                        color
+                    }
+                    _ => self.void,
+                };
+
+                column.set(y, color);
+            }
+        };
+
+        // rows from clip down are already drawn by nearer walls
+        let mut clip = view.height;
+        let mut trace = Trace {
+            origin: pos,
+            dir: ray.dir,
+            hits: Vec::new(),
+            visited: Vec::new(),
+            reach: self.max_distance,
+            occluders: Vec::new(),
+        };
+
+        // DDA, marching past walls that are too short to hide what's behind them
+        while !ray.hit {
+            let (map_pos, side, distance) = dda.step();
+
+            // the ray escaped through an open border or went as far as it's allowed to
+            let cell = match self.cell(map_pos.x, map_pos.y) {
+                Some(cell) if distance <= self.max_distance => cell,
+                _ => {
+                    trace.reach = distance.min(self.max_distance);
+                    break;
+                }
             };
 
-            // step direction for map_pos
-            let step = Vector {
-                x: if ray.dir.x < 0. { -1. } else { 1. },
-                y: if ray.dir.y < 0. { -1. } else { 1. },
+            if view.visits {
+                trace.visited.push(map_pos);
+            }
+            if cell.solid == MapCellType::Empty {
+                continue;
+            }
+            // the open part of a door lets the ray through
+            let hit = match self.wall_hit(&dda, cell, side, distance) {
+                Some(hit) => hit,
+                None => continue,
             };
+            trace.hits.push(hit);
+            let (side, distance) = (hit.side, hit.distance);
 
-            // ray distance from side of map cell (helps with determining direction to inc)
-            let mut side_dist: Vector<f64> = Vector {
-                x: if ray.dir.x < 0. {
-                    // top left edge of map cell
-                    (self.player.pos.x - map_pos.x as f64) * delta_dist.x
-                } else {
-                    // top right edge of map cell
-                    (map_pos.x as f64 + 1. - self.player.pos.x) * delta_dist.x
-                },
+            // on-screen size of one unit of wall height; the eye sits at half a unit
+            let unit = view.focal_length / distance;
 
-                y: if ray.dir.y < 0. {
-                    // top left edge of map cell
-                    (self.player.pos.y - map_pos.y as f64) * delta_dist.y
-                } else {
-                    // top right edge of map cell
-                    (map_pos.y as f64 + 1. - self.player.pos.y) * delta_dist.y
-                },
-            };
+            let wall_top = horizon - unit * (cell.height - 0.5);
+            let wall_bottom = horizon + unit / 2.;
+            let column_start = wall_top.max(0.).ceil() as usize;
+            let column_end = (wall_bottom as usize).min(clip);
 
-            // DDA
-            let mut side = 0;
-            while !ray.hit {
-                if side_dist.x < side_dist.y {
-                    side_dist.x += delta_dist.x;
-                    map_pos.x += step.x as i32;
-                    side = 0;
-                } else {
-                    side_dist.y += delta_dist.y;
-                    map_pos.y += step.y as i32;
-                    side = 1;
-                }
+            if column_start < column_end {
+                if let Some(column) = column.as_deref_mut() {
+                    fill(column, column_end..clip);
 
-                if self.map[map_pos.y as usize][map_pos.x as usize].solid != MapCellType::Empty {
-                    ray.hit = true;
+                    let rows = column_start..column_end;
+                    match cell.texture.and_then(|id| self.textures.get(id)) {
+                        Some(texture) => {
+                            // y-sides are drawn at half brightness
+                            let shade = fog(distance) / if side == Side::Y { 2. } else { 1. };
+                            column.copy_strip(rows, texture, hit.u, wall_top, unit, shade);
+                        }
+                        None => {
+                            let mut color = cell.color;
+                            if side == Side::Y {
+                                color.div_assign(2)
+                            }
+                            color.mul_assign(fog(distance));
+                            column.fill(rows, color);
+                        }
+                    }
                 }
-            }
 
-            let mut cell = self.map[map_pos.y as usize][map_pos.x as usize];
+                clip = column_start;
+                trace.occluders.push(Occluder {
+                    distance,
+                    top: clip,
+                });
+            }
 
-            if side == 1 {
-                cell.color.div_assign(2)
+            // nothing behind the tallest walls can poke out above them
+            if clip == 0 || cell.height >= self.max_height {
+                ray.hit = true;
+                trace.reach = distance;
             }
+        }
 
-            let distance: f64 = if side == 0 {
-                (map_pos.x as f64 - self.player.pos.x + (1. - step.x) / 2.) / ray.dir.x
-            } else {
-                (map_pos.y as f64 - self.player.pos.y + (1. - step.y) / 2.) / ray.dir.y
+        if let Some(column) = column {
+            fill(column, 0..clip);
+        }
+        trace
+    }
+
+    fn draw_sprites(&self, target: &mut ColumnBuffer, view: &View, traces: &[Trace]) {
+        let (width, height) = (view.width, view.height);
+        let focal_length = view.focal_length;
+
+        // farthest first so nearer sprites paint over them
+        let mut sprites: Vec<(&Sprite, f64, f64)> = self
+            .sprites
+            .iter()
+            .filter_map(|sprite| {
+                let (center, distance) = self.project(sprite.pos, width)?;
+                Some((sprite, center, distance))
+            })
+            .filter(|&(_, _, distance)| distance <= self.max_distance)
+            .collect();
+        sprites.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        for (sprite, center, distance) in sprites {
+            let texture = match self.sprite_textures.get(sprite.texture) {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            // one unit wide and one unit tall, standing on the floor
+            let size = match self.projection {
+                Projection::Planar => focal_length / distance,
+                Projection::Fisheye => width as f64 / self.fov.to_radians() / distance,
             };
+            let unit = focal_length / distance;
+            let left = center - size / 2.;
+            let top = view.horizon - unit / 2.;
+
+            let x_start = left.max(0.).ceil() as usize;
+            let x_end = ((left + size).max(0.) as usize).min(width);
+
+            for (x, trace) in traces.iter().enumerate().take(x_end).skip(x_start) {
+                // nearer walls hide the sprite from their top down
+                let clip = trace
+                    .occluders
+                    .iter()
+                    .filter(|occluder| occluder.distance < distance)
+                    .map(|occluder| occluder.top)
+                    .min()
+                    .unwrap_or(height);
+
+                let y_start = top.max(0.).ceil() as usize;
+                let y_end = ((top + unit) as usize).min(clip);
+
+                // transparent pixels let the scene through
+                if y_start < y_end {
+                    target.column_mut(x).blend_strip(
+                        y_start..y_end,
+                        texture,
+                        (x as f64 - left) / size,
+                        top,
+                        unit,
+                        fog(distance, self.player.pitch),
+                    );
+                }
+            }
+        }
+    }
+
+    fn ray_dir(&self, column: usize, width: usize) -> Vector<f64> {
+        match self.projection {
+            Projection::Planar => {
+                // -1 at the left edge of the screen, 1 at the right
+                let camera_x = 2. * column as f64 / width as f64 - 1.;
+                self.player.dir + self.camera_plane() * camera_x
+            }
+            Projection::Fisheye => {
+                let angle = self.fov / width as f64 * column as f64 - self.fov / 2.;
+                self.player.dir.rotate(angle.to_radians())
+            }
+        }
+    }
 
-            // let correct_distance = distance * (self.player.dir.angle() - ray.dir.angle()).cos();
-            let correct_distance = distance;
+    // half the screen width, at one unit in front of the player
+    fn camera_plane(&self) -> Vector<f64> {
+        self.player.dir.orthogonal(Direction::Left) * (self.fov.to_radians() / 2.).tan()
+    }
 
-            let mut height = (HEIGHT as f64 / correct_distance).abs() * 15.;
-            if height > HEIGHT as f64 {
-                height = HEIGHT as f64;
+    // pixels spanned by one unit at a distance of one unit
+    fn focal_length(&self, width: usize) -> f64 {
+        width as f64 / 2. / (self.fov.to_radians() / 2.).tan()
+    }
+
+    // screen column and distance of a point, measured the same way as walls
+    fn project(&self, point: Vector<f64>, width: usize) -> Option<(f64, f64)> {
+        let rel = point - self.eye();
+
+        match self.projection {
+            Projection::Planar => {
+                let plane = self.camera_plane();
+                let depth = rel.dot(&self.player.dir);
+                if depth <= 0.01 {
+                    return None;
+                }
+
+                let camera_x = rel.dot(&plane) / plane.dot(&plane) / depth;
+                Some(((camera_x + 1.) / 2. * width as f64, depth))
             }
+            Projection::Fisheye => {
+                // angle off the view direction, wrapped to -PI..PI
+                let angle = (rel.angle() - self.player.dir.angle() + PI).rem_euclid(2. * PI) - PI;
+                let fov = self.fov.to_radians();
 
-            let shear = (self.player.pitch * HEIGHT as f64 / 2.0) as usize;
+                Some((
+                    (angle + fov / 2.) / fov * width as f64,
+                    rel.dot(&rel).sqrt(),
+                ))
+            }
+        }
+    }
+
+    pub fn position(&self) -> Vector<f64> {
+        self.player.pos
+    }
 
-            // fog
-            cell.color.mul_assign(1. / (1. + correct_distance * correct_distance * 0.0001 + shear as f64 * 0.002));
+    pub fn direction(&self) -> Vector<f64> {
+        self.player.dir
+    }
 
-            let column_start = 0;
-            let column_end = HEIGHT as usize / 2 + height as usize / 2 + shear;
+    // like set_camera, but refuses positions the player couldn't stand at
+    pub fn set_spawn(&mut self, pos: Vector<f64>, dir: Vector<f64>) -> Result<()> {
+        let reason = match self.cell(pos.x.floor() as i32, pos.y.floor() as i32) {
+            None => Some("it's outside the map"),
+            Some(cell) if cell.solid != MapCellType::Empty => Some("it's inside a wall"),
+            _ if dir.length() == 0. => Some("the direction has no length"),
+            _ if !dir.length().is_finite() => Some("the direction isn't a finite number"),
+            _ => None,
+        };
 
-            verline(frame, i, column_start, column_end, cell.color, 1);
+        if let Some(reason) = reason {
+            return Err(Error::InvalidSpawn {
+                x: pos.x,
+                y: pos.y,
+                reason,
+            });
         }
 
+        self.set_camera(pos, dir, self.player.pitch);
         Ok(())
     }
 
-    pub fn update_player(&mut self) {
-        let new_pos_x = Vector::new(self.player.pos.x + self.player.vel.x, self.player.pos.y);
-        if self.is_valid_position(&new_pos_x) {
-            self.player.pos = new_pos_x;
+    // dir can be any length, the projection needs it unit length so it's scaled to that;
+    // one with no usable length leaves the player facing the way they were
+    pub fn set_camera(&mut self, pos: Vector<f64>, dir: Vector<f64>, pitch: f64) {
+        let length = dir.length();
+        self.player.pos = pos;
+        self.player.prev_pos = pos;
+        if length > 0. && length.is_finite() {
+            self.player.dir = dir * (1. / length);
         }
+        self.player.vel = Vector::new(0., 0.);
+        self.player.pitch = pitch;
+    }
+
+    // how far between the last two ticks to draw the player, 0 to 1
+    pub fn set_interpolation(&mut self, alpha: f64) {
+        self.interpolation = alpha.clamp(0., 1.);
+    }
 
-        let new_pos_y = Vector::new(self.player.pos.x, self.player.pos.y + self.player.vel.y);
-        if self.is_valid_position(&new_pos_y) {
-            self.player.pos = new_pos_y;
+    // where the player is drawn from
+    pub fn eye(&self) -> Vector<f64> {
+        self.player.prev_pos + (self.player.pos - self.player.prev_pos) * self.interpolation
+    }
+
+    // columns are split between this many threads, 1 draws on the calling thread
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    // rays stop after this many units, and anything past it is drawn as the void
+    pub fn set_max_distance(&mut self, max_distance: f64) {
+        self.max_distance = max_distance;
+    }
+
+    // what rays that leave the map or reach max_distance see
+    pub fn set_void(&mut self, color: [u8; 4]) {
+        self.void = color;
+    }
+
+    // drawn over the 3D view, None turns it off
+    pub fn set_minimap(&mut self, minimap: Option<Minimap>) {
+        self.minimap = minimap;
+    }
+
+    pub fn minimap(&self) -> Option<Minimap> {
+        self.minimap
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    pub fn add_sprite(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    pub fn map(&self) -> &[Vec<MapCell>] {
+        &self.map
+    }
+
+    // swaps in a new cell and returns the old one, or None outside the map
+    pub fn set_cell(&mut self, x: usize, y: usize, cell: MapCell) -> Option<MapCell> {
+        let old = std::mem::replace(self.map.get_mut(y)?.get_mut(x)?, cell);
+        self.doors.remove(&(x as i32, y as i32));
+
+        // rays look for walls up to the tallest one, which may have just changed
+        self.max_height = self
+            .map
+            .iter()
+            .flatten()
+            .fold(0., |max, cell| cell.height.max(max));
+        Some(old)
+    }
+
+    // the wall atlas, indexed by MapCell::texture
+    pub fn textures(&self) -> &[Texture] {
+        &self.textures
+    }
+
+    // None outside the map
+    fn surface_color(&self, point: Vector<f64>, ceiling: bool) -> Option<[u8; 4]> {
+        let cell = self.cell(point.x.floor() as i32, point.y.floor() as i32)?;
+
+        let surface = if ceiling {
+            cell.ceiling.unwrap_or(self.ceiling)
+        } else {
+            cell.floor.unwrap_or(self.floor)
+        };
+
+        Some(match surface {
+            Surface::Color(color) => color,
+            Surface::Texture(id) => match self.textures.get(id) {
+                Some(texture) => texture.sample(point.x, point.y),
+                None => [0, 0, 0, 255],
+            },
+        })
+    }
+
+    // None outside the map, including past the end of a short row
+    fn cell(&self, x: i32, y: i32) -> Option<MapCell> {
+        if x < 0 || y < 0 {
+            return None;
         }
+        self.map.get(y as usize)?.get(x as usize).copied()
+    }
+
+    pub fn movement(&self) -> &MovementController {
+        &self.player.movement
+    }
 
-        self.player.vel *= 0.8;
+    pub fn movement_mut(&mut self) -> &mut MovementController {
+        &mut self.player.movement
     }
 
-    fn is_valid_position(&self, pos: &Vector<f64>) -> bool {
-        if let Some(row) = self.map.get(pos.y as usize) {
-            if let Some(cell) = row.get(pos.x as usize) {
-                if cell.solid == MapCellType::Empty {
-                    return true;
+    // advances the player by dt seconds, accelerating towards the held movement keys
+    pub fn update_player(&mut self, dt: f64) {
+        self.player.prev_pos = self.player.pos;
+        self.player.vel = self
+            .player
+            .movement
+            .accelerate(self.player.vel, self.player.wish, dt);
+
+        let pos = self.player.pos;
+        self.player.pos = move_circle(
+            pos,
+            self.player.vel * dt,
+            self.player.movement.radius,
+            |x, y| self.blocks(x, y),
+        );
+
+        // walls take away whatever part of the velocity went into them
+        if dt > 0. {
+            self.player.vel = (self.player.pos - pos) * (1. / dt);
+        }
+    }
+
+    // forgets the movement keys, before the next frame's input is applied
+    pub fn clear_input(&mut self) {
+        self.player.wish = Vector::new(0., 0.);
+    }
+
+    // whether the player can't walk into a cell, which includes everything outside the map
+    fn blocks(&self, x: i32, y: i32) -> bool {
+        match self.cell(x, y).map(|cell| cell.solid) {
+            None | Some(MapCellType::Wall) => true,
+            Some(MapCellType::Empty) => false,
+            // only once they're all the way open
+            Some(MapCellType::Door | MapCellType::PushWall) => self.door(x, y).open < 1.,
+        }
+    }
+
+    // opens or closes the door in reach straight ahead of the player, or pushes the push
+    // wall there; false if there's nothing to use or it won't budge
+    pub fn interact(&mut self) -> bool {
+        let length = self.player.dir.length();
+        if length == 0. {
+            return false;
+        }
+
+        // the first cell that isn't empty, whether or not rays would pass it
+        let mut dda = Dda::new(self.player.pos, self.player.dir * (1. / length));
+        loop {
+            let (map_pos, _, distance) = dda.step();
+            match self.cell(map_pos.x, map_pos.y) {
+                _ if distance > REACH => return false,
+                None => return false,
+                Some(cell) if cell.solid == MapCellType::Empty => continue,
+                Some(_) => {
+                    let opening = !self.door(map_pos.x, map_pos.y).opening;
+                    return self.set_door(map_pos.x as usize, map_pos.y as usize, opening);
+                }
+            }
+        }
+    }
+
+    // starts the door at x, y opening or closing, or the push wall there moving away from
+    // the player for good; a door won't close on the player, and false means nothing moved
+    pub fn set_door(&mut self, x: usize, y: usize, opening: bool) -> bool {
+        let (x, y) = (x as i32, y as i32);
+        let mut door = self.door(x, y);
+
+        match self.cell(x, y).map(|cell| cell.solid) {
+            Some(MapCellType::Door) => {
+                if !opening && touches(self.player.pos, self.player.movement.radius, (x, y)) {
+                    return false;
                 }
             }
+            Some(MapCellType::PushWall) if opening && !door.opening => {
+                let away = Vector::new(x as f64 + 0.5, y as f64 + 0.5) - self.player.pos;
+                door.toward = if away.x.abs() >= away.y.abs() {
+                    Vector::new(if away.x < 0. { -1 } else { 1 }, 0)
+                } else {
+                    Vector::new(0, if away.y < 0. { -1 } else { 1 })
+                };
+            }
+            _ => return false,
+        }
+
+        door.opening = opening;
+        self.doors.insert((x, y), door);
+        true
+    }
+
+    // how far open the door or push wall at x, y is, 0 to 1
+    pub fn door_open(&self, x: usize, y: usize) -> Option<f64> {
+        let (x, y) = (x as i32, y as i32);
+        match self.cell(x, y)?.solid {
+            MapCellType::Door | MapCellType::PushWall => Some(self.door(x, y).open),
+            _ => None,
+        }
+    }
+
+    // moves every opening or closing door along by dt seconds
+    pub fn update_doors(&mut self, dt: f64) {
+        for (&(x, y), door) in self.doors.iter_mut() {
+            let speed = match self.map[y as usize][x as usize].solid {
+                MapCellType::PushWall => PUSH_WALL_SPEED,
+                _ => DOOR_SPEED,
+            };
+            let step = if door.opening { speed } else { -speed } * dt;
+            door.open = (door.open + step).clamp(0., 1.);
         }
 
-        false
+        // closed doors go back to being like the rest
+        self.doors.retain(|_, door| door.opening || door.open > 0.);
+    }
+
+    // a door's state, closed unless it's been opened; closed doors slide into the
+    // walls on either side of them, and push walls haven't been pushed any way yet
+    fn door(&self, x: i32, y: i32) -> Door {
+        self.doors.get(&(x, y)).copied().unwrap_or_else(|| {
+            let wall = |x, y| {
+                self.cell(x, y)
+                    .is_some_and(|cell| cell.solid != MapCellType::Empty)
+            };
+            Door {
+                open: 0.,
+                opening: false,
+                toward: if wall(x - 1, y) && wall(x + 1, y) {
+                    Vector::new(1, 0)
+                } else {
+                    Vector::new(0, 1)
+                },
+            }
+        })
     }
 
+    // movement directions are held until clear_input, mouse motion turns right away
     pub fn change_direction(&mut self, dir: Direction) {
+        // radians per unit of mouse motion, so turning doesn't depend on frame rate
         const ROTATESPEED: f64 = 0.001;
-        let acceleration = unsafe { ACCELERATION };
 
         match dir {
             Direction::Down => {
-                self.player.vel.x -= self.player.dir.x * acceleration;
-                self.player.vel.y -= self.player.dir.y * acceleration;
+                self.player.wish.x -= self.player.dir.x;
+                self.player.wish.y -= self.player.dir.y;
             }
             Direction::Up => {
-                self.player.vel.x += self.player.dir.x * acceleration;
-                self.player.vel.y += self.player.dir.y * acceleration;
+                self.player.wish.x += self.player.dir.x;
+                self.player.wish.y += self.player.dir.y;
             }
             Direction::Left => {
                 let ortho = self.player.dir.orthogonal(Direction::Left);
-                self.player.vel.x -= ortho.x * acceleration;
-                self.player.vel.y -= ortho.y * acceleration;
+                self.player.wish.x -= ortho.x;
+                self.player.wish.y -= ortho.y;
             }
             Direction::Right => {
                 let ortho = self.player.dir.orthogonal(Direction::Right);
-                self.player.vel.x -= ortho.x * acceleration;
-                self.player.vel.y -= ortho.y * acceleration;
+                self.player.wish.x -= ortho.x;
+                self.player.wish.y -= ortho.y;
             }
             Direction::Mouse(dx, dy) => {
                 self.player.dir = self.player.dir.rotate(dx * ROTATESPEED);
@@ -345,26 +1008,122 @@ impl DivAssign for [u8; 4] {
     }
 }
 
-fn generate_map() -> Vec<Vec<MapCell>> {
-    let img = image::open("assets/map.png").unwrap();
-    let img = img.to_rgba8();
-    let (width, height) = img.dimensions();
+impl Dda {
+    fn new(pos: Vector<f64>, dir: Vector<f64>) -> Self {
+        let map_pos = Vector::new(pos.x.floor() as i32, pos.y.floor() as i32);
+        let delta_dist = Vector::new((1.0 / dir.x).abs(), (1.0 / dir.y).abs());
+        let step = Vector::new(
+            if dir.x < 0. { -1 } else { 1 },
+            if dir.y < 0. { -1 } else { 1 },
+        );
+
+        // from pos to the first edge the ray crosses along each axis
+        let side_dist = Vector {
+            x: if dir.x < 0. {
+                (pos.x - map_pos.x as f64) * delta_dist.x
+            } else {
+                (map_pos.x as f64 + 1. - pos.x) * delta_dist.x
+            },
+            y: if dir.y < 0. {
+                (pos.y - map_pos.y as f64) * delta_dist.y
+            } else {
+                (map_pos.y as f64 + 1. - pos.y) * delta_dist.y
+            },
+        };
+
+        Self {
+            pos,
+            dir,
+            map_pos,
+            delta_dist,
+            step,
+            side_dist,
+        }
+    }
+
+    // into the next cell, and the side and distance it was entered at
+    fn step(&mut self) -> (Vector<i32>, Side, f64) {
+        let side = if self.side_dist.x < self.side_dist.y {
+            self.side_dist.x += self.delta_dist.x;
+            self.map_pos.x += self.step.x;
+            Side::X
+        } else {
+            self.side_dist.y += self.delta_dist.y;
+            self.map_pos.y += self.step.y;
+            Side::Y
+        };
+
+        // the near edge of the cell, which is its far edge when stepping backwards
+        let distance = if side == Side::X {
+            (self.map_pos.x as f64 - self.pos.x + f64::from(self.step.x < 0)) / self.dir.x
+        } else {
+            (self.map_pos.y as f64 - self.pos.y + f64::from(self.step.y < 0)) / self.dir.y
+        };
+
+        (self.map_pos, side, distance)
+    }
+
+    // the wall in the cell just stepped into, with its texture shifted along by shift
+    fn hit(&self, wall: MapCell, side: Side, distance: f64, shift: f64) -> Hit {
+        let point = self.pos + self.dir * distance;
+
+        // where along the wall face the ray hit, 0 to 1
+        let mut u = if side == Side::X { point.y } else { point.x };
+        u = (u - u.floor() - shift).max(0.);
+
+        // flip so textures aren't mirrored on opposite faces
+        if (side == Side::X && self.dir.x > 0.) || (side == Side::Y && self.dir.y < 0.) {
+            u = 1. - u;
+        }
+
+        Hit {
+            cell: self.map_pos,
+            wall,
+            side,
+            distance,
+            point,
+            u,
+        }
+    }
 
-    let mut buffer: Vec<Vec<MapCell>> = vec![vec![MapCell::empty(); width as usize]; height as usize];
+    // where the ray meets a box in the cell just stepped into, with lo and hi its
+    // corners from the cell's own corner; a box with no depth is a panel
+    fn hit_box(&self, wall: MapCell, lo: Vector<f64>, hi: Vector<f64>, shift: f64) -> Option<Hit> {
+        let cell = Vector::new(self.map_pos.x as f64, self.map_pos.y as f64);
+        let (lo, hi) = (cell + lo, cell + hi);
 
-    for y in 0..height {
-        for x in 0..width {
-            let pixel = img.get_pixel(x, y).0;
-            let solid = if pixel == [0, 0, 0, 0] {
-                MapCellType::Empty
+        // how far along the ray it's between each pair of opposite faces
+        let slab = |pos: f64, dir: f64, lo: f64, hi: f64| {
+            if dir == 0. {
+                if (lo..=hi).contains(&pos) {
+                    (f64::NEG_INFINITY, f64::INFINITY)
+                } else {
+                    (f64::INFINITY, f64::NEG_INFINITY)
+                }
             } else {
-                MapCellType::Wall
-            };
-            buffer[y as usize][x as usize] = MapCell::new(pixel, solid, 0.);
+                let (a, b) = ((lo - pos) / dir, (hi - pos) / dir);
+                (a.min(b), a.max(b))
+            }
+        };
+        let (x_in, x_out) = slab(self.pos.x, self.dir.x, lo.x, hi.x);
+        let (y_in, y_out) = slab(self.pos.y, self.dir.y, lo.y, hi.y);
+
+        // it enters through whichever pair of faces it reaches last
+        let (distance, side) = if x_in > y_in {
+            (x_in, Side::X)
+        } else {
+            (y_in, Side::Y)
+        };
+        if distance < 0. || distance > x_out.min(y_out) {
+            return None;
         }
+        Some(self.hit(wall, side, distance, shift))
     }
+}
 
-    buffer
+// looking down dims everything, by the same amount at any resolution
+fn fog(distance: f64, pitch: f64) -> f64 {
+    1. / (1. + distance * distance * 0.0001 + pitch * 1.08)
 }
 
 fn distance_squared(p1: Vector<f64>, p2: Vector<f64>) -> f64 {
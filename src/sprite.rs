@@ -0,0 +1,14 @@
+use crate::vector::Vector;
+
+// a billboard standing on the floor, always facing the camera
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sprite {
+    pub pos: Vector<f64>,
+    pub texture: usize,
+}
+
+impl Sprite {
+    pub fn new(pos: Vector<f64>, texture: usize) -> Self {
+        Self { pos, texture }
+    }
+}
@@ -0,0 +1,403 @@
+// A map written by hand: a header of `key = value` lines, a few sections, and the
+// grid itself drawn in characters that a legend gives meaning to.
+//
+//     # lines starting with '#' are comments, anywhere above the grid
+//     name = "Two rooms"
+//     spawn = 2.5, 2.5, 1, 0
+//
+//     [markers]
+//     exit = 8.5, 2.5
+//
+//     [sprites]
+//     1 = 6.5, 1.5
+//
+//     [legend]
+//     '#' = wall color=#884422 texture=0
+//     'h' = wall color=#884422 texture=0 height=0.5
+//     '.' = empty
+//     '_' = empty floor=#404040 ceiling=3
+//     'D' = door texture=6
+//     'P' = pushwall color=#884422 texture=0
+//
+//     [grid]
+//     ##########
+//     #...h____#
+//     #...D____#
+//     ###P######
+//
+// the spawn is x, y and optionally the direction to face, markers are x, y, sprites
+// are a texture number and x, y, and floor and ceiling take a color or a texture
+// number; doors and push walls take the same properties as walls; the grid runs to
+// the end of the file and every character in it needs a legend entry
+
+use std::path::Path;
+
+use crate::{
+    error::{Error, Result},
+    map::{validate, Map, MapCell, MapCellType, Surface},
+    sprite::Sprite,
+    vector::Vector,
+};
+
+enum Section {
+    Header,
+    Markers,
+    Sprites,
+    Legend,
+}
+
+pub fn load_text_map(path: &str) -> Result<Map> {
+    let source = std::fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.into(),
+        source,
+    })?;
+    let map = parse(&source).map_err(|reason| Error::InvalidMap {
+        path: path.into(),
+        reason,
+    })?;
+
+    validate(Path::new(path), &map.cells)?;
+    Ok(map)
+}
+
+// writes a map in this format, with one legend entry for each kind of cell in it
+pub fn save_text_map(map: &Map, path: &str) -> Result<()> {
+    let text = write(map).map_err(|reason| Error::InvalidMap {
+        path: path.into(),
+        reason,
+    })?;
+    std::fs::write(path, text).map_err(|source| Error::Io {
+        path: path.into(),
+        source,
+    })
+}
+
+fn write(map: &Map) -> std::result::Result<String, String> {
+    let mut text = String::new();
+
+    if let Some(name) = &map.name {
+        if name.contains('\n') {
+            return Err(format!("the name {:?} is more than one line", name));
+        }
+        text += &format!("name = \"{}\"\n", name);
+    }
+    match map.spawn {
+        Some((pos, Some(dir))) => {
+            text += &format!("spawn = {}, {}, {}, {}\n", pos.x, pos.y, dir.x, dir.y)
+        }
+        Some((pos, None)) => text += &format!("spawn = {}, {}\n", pos.x, pos.y),
+        None => {}
+    }
+
+    if !map.markers.is_empty() {
+        text += "\n[markers]\n";
+        for (name, pos) in &map.markers {
+            if name.contains(['=', '"', '\n']) {
+                return Err(format!("the marker name {:?} can't be written", name));
+            }
+            text += &format!("\"{}\" = {}, {}\n", name, pos.x, pos.y);
+        }
+    }
+
+    if !map.sprites.is_empty() {
+        text += "\n[sprites]\n";
+        for sprite in &map.sprites {
+            text += &format!("{} = {}, {}\n", sprite.texture, sprite.pos.x, sprite.pos.y);
+        }
+    }
+
+    // empty cells only differ by what's above and below them
+    let kind = |cell: &MapCell| match cell.solid {
+        MapCellType::Empty => MapCell {
+            floor: cell.floor,
+            ceiling: cell.ceiling,
+            ..MapCell::empty()
+        },
+        _ => *cell,
+    };
+
+    // the first kinds of wall and empty cell get the usual symbols
+    let mut legend: Vec<(char, MapCell)> = Vec::new();
+    let mut grid = String::new();
+    for row in &map.cells {
+        for cell in row {
+            let cell = kind(cell);
+            let symbol = match legend.iter().find(|&&(_, c)| c == cell) {
+                Some(&(symbol, _)) => symbol,
+                None => {
+                    let usual = match cell.solid {
+                        MapCellType::Empty => '.',
+                        MapCellType::Wall => '#',
+                        MapCellType::Door => 'D',
+                        MapCellType::PushWall => 'P',
+                    };
+                    let symbol = std::iter::once(usual)
+                        .chain(SYMBOLS.chars())
+                        .chain((0xc0..).filter_map(char::from_u32))
+                        .find(|&symbol| legend.iter().all(|&(c, _)| c != symbol))
+                        .ok_or("there are too many kinds of cell")?;
+                    legend.push((symbol, cell));
+                    symbol
+                }
+            };
+            grid.push(symbol);
+        }
+        grid.push('\n');
+    }
+
+    text += "\n[legend]\n";
+    for (symbol, cell) in &legend {
+        text += &format!("'{}' = {}\n", symbol, describe(cell));
+    }
+    text += "\n[grid]\n";
+    text += &grid;
+
+    Ok(text)
+}
+
+// legend symbols after the usual ones, before running into the rest of unicode
+const SYMBOLS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+// the legend entry parse_cell reads back into the same cell
+fn describe(cell: &MapCell) -> String {
+    let mut words = Vec::new();
+
+    if cell.solid == MapCellType::Empty {
+        words.push("empty".to_string());
+    } else {
+        words.push(
+            match cell.solid {
+                MapCellType::Door => "door",
+                MapCellType::PushWall => "pushwall",
+                _ => "wall",
+            }
+            .to_string(),
+        );
+        words.push(format!("color={}", hex(cell.color)));
+        if let Some(texture) = cell.texture {
+            words.push(format!("texture={}", texture));
+        }
+        if cell.height != 1. {
+            words.push(format!("height={}", cell.height));
+        }
+    }
+
+    for (key, surface) in [("floor", cell.floor), ("ceiling", cell.ceiling)] {
+        match surface {
+            Some(Surface::Color(color)) => words.push(format!("{}={}", key, hex(color))),
+            Some(Surface::Texture(texture)) => words.push(format!("{}={}", key, texture)),
+            None => {}
+        }
+    }
+
+    words.join(" ")
+}
+
+fn hex([r, g, b, a]: [u8; 4]) -> String {
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+fn parse(source: &str) -> std::result::Result<Map, String> {
+    let mut map = Map::new(Vec::new());
+    let mut legend: Vec<(char, MapCell)> = Vec::new();
+    let mut section = Section::Header;
+    let mut grid = None;
+
+    let mut lines = source.lines().enumerate();
+    for (i, line) in lines.by_ref() {
+        let at = |reason: String| format!("line {}: {}", i + 1, reason);
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line {
+            "[markers]" => section = Section::Markers,
+            "[sprites]" => section = Section::Sprites,
+            "[legend]" => section = Section::Legend,
+            "[grid]" => {
+                grid = Some(i + 1);
+                break;
+            }
+            _ if line.starts_with('[') => return Err(at(format!("unknown section {}", line))),
+            _ => {
+                let (key, value) = line
+                    .split_once('=')
+                    .ok_or_else(|| at(format!("expected key = value, got {:?}", line)))?;
+                let (key, value) = (key.trim(), value.trim());
+
+                match section {
+                    Section::Header => match key {
+                        "name" => map.name = Some(unquote(value).to_string()),
+                        "spawn" => match parse_numbers(value).map_err(at)?[..] {
+                            [x, y] => map.spawn = Some((Vector::new(x, y), None)),
+                            [x, y, dir_x, dir_y] => {
+                                map.spawn =
+                                    Some((Vector::new(x, y), Some(Vector::new(dir_x, dir_y))))
+                            }
+                            _ => return Err(at("spawn needs x, y or x, y, dir x, dir y".into())),
+                        },
+                        _ => return Err(at(format!("unknown key {:?}", key))),
+                    },
+                    Section::Markers => {
+                        let name = unquote(key);
+                        if map.marker(name).is_some() {
+                            return Err(at(format!("marker {:?} is defined twice", name)));
+                        }
+                        match parse_numbers(value).map_err(at)?[..] {
+                            [x, y] => map.markers.push((name.to_string(), Vector::new(x, y))),
+                            _ => return Err(at(format!("marker {:?} needs x, y", name))),
+                        }
+                    }
+                    Section::Sprites => {
+                        let texture = parse_index(key).map_err(at)?;
+                        match parse_numbers(value).map_err(at)?[..] {
+                            [x, y] => map.sprites.push(Sprite::new(Vector::new(x, y), texture)),
+                            _ => return Err(at("a sprite needs x, y".into())),
+                        }
+                    }
+                    Section::Legend => {
+                        let mut symbol = unquote(key).chars();
+                        let symbol = match (symbol.next(), symbol.next()) {
+                            (Some(c), None) => c,
+                            _ => {
+                                return Err(at(format!(
+                                    "legend keys are one quoted character, like '#', got {}",
+                                    key
+                                )))
+                            }
+                        };
+                        if legend.iter().any(|&(c, _)| c == symbol) {
+                            return Err(at(format!("{:?} is in the legend twice", symbol)));
+                        }
+                        legend.push((symbol, parse_cell(value).map_err(at)?));
+                    }
+                }
+            }
+        }
+    }
+
+    let start = grid.ok_or("there is no [grid] section")?;
+    let rows: Vec<(usize, &str)> = lines.collect();
+
+    // blank lines after the grid aren't rows
+    let end = rows
+        .iter()
+        .rposition(|(_, row)| !row.trim().is_empty())
+        .map_or(0, |last| last + 1);
+
+    for &(i, row) in &rows[..end] {
+        let cells = row
+            .chars()
+            .map(|symbol| {
+                legend
+                    .iter()
+                    .find(|&&(c, _)| c == symbol)
+                    .map(|&(_, cell)| cell)
+                    .ok_or_else(|| format!("line {}: {:?} isn't in the legend", i + 1, symbol))
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        map.cells.push(cells);
+    }
+
+    if map.cells.is_empty() {
+        return Err(format!("line {}: the grid is empty", start));
+    }
+    Ok(map)
+}
+
+// `wall`, `door`, `pushwall` or `empty` followed by any of color=, texture=, height=, floor= and ceiling=
+fn parse_cell(definition: &str) -> std::result::Result<MapCell, String> {
+    let mut words = definition.split_whitespace();
+
+    let mut cell = match words.next() {
+        Some("wall") => MapCell::new([128, 128, 128, 255], MapCellType::Wall, 1.),
+        Some("door") => MapCell::new([128, 128, 128, 255], MapCellType::Door, 1.),
+        Some("pushwall") => MapCell::new([128, 128, 128, 255], MapCellType::PushWall, 1.),
+        Some("empty") => MapCell::empty(),
+        _ => {
+            return Err(format!(
+                "a legend entry starts with wall, door, pushwall or empty, got {:?}",
+                definition
+            ))
+        }
+    };
+
+    for property in words {
+        let (key, value) = property
+            .split_once('=')
+            .ok_or_else(|| format!("expected property=value, got {:?}", property))?;
+
+        match key {
+            "floor" => cell.floor = Some(parse_surface(value)?),
+            "ceiling" => cell.ceiling = Some(parse_surface(value)?),
+            "color" | "texture" | "height" if cell.solid == MapCellType::Empty => {
+                return Err(format!("only walls have a {}", key))
+            }
+            "color" => cell.color = parse_color(value)?,
+            "texture" => cell.texture = Some(parse_index(value)?),
+            "height" => {
+                cell.height = value
+                    .parse()
+                    .ok()
+                    .filter(|height: &f64| *height >= 0.)
+                    .ok_or_else(|| format!("bad height {:?}", value))?
+            }
+            _ => return Err(format!("unknown property {:?}", key)),
+        }
+    }
+
+    Ok(cell)
+}
+
+fn parse_surface(s: &str) -> std::result::Result<Surface, String> {
+    if s.starts_with('#') {
+        parse_color(s).map(Surface::Color)
+    } else {
+        parse_index(s).map(Surface::Texture)
+    }
+}
+
+// #rrggbb or #rrggbbaa
+fn parse_color(s: &str) -> std::result::Result<[u8; 4], String> {
+    let err = || format!("bad color {:?}, expected #rrggbb or #rrggbbaa", s);
+
+    let hex = s.strip_prefix('#').ok_or_else(err)?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(err());
+    }
+
+    let mut color = [255; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| err())?;
+    }
+    Ok(color)
+}
+
+fn parse_index(s: &str) -> std::result::Result<usize, String> {
+    s.parse()
+        .map_err(|_| format!("bad texture {:?}, expected a number", s))
+}
+
+fn parse_numbers(s: &str) -> std::result::Result<Vec<f64>, String> {
+    s.split(',')
+        .map(|n| {
+            n.trim()
+                .parse()
+                .map_err(|_| format!("bad number {:?}", n.trim()))
+        })
+        .collect()
+}
+
+fn unquote(s: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(quote).and_then(|s| s.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    s
+}
@@ -0,0 +1,73 @@
+use image::RgbaImage;
+
+use crate::error::{Error, Result};
+
+pub struct Texture {
+    image: RgbaImage,
+    // the same pixels column by column, for drawing vertical strips
+    columns: Vec<u8>,
+}
+
+impl Texture {
+    pub fn new(image: RgbaImage) -> Self {
+        let mut columns = Vec::with_capacity(image.as_raw().len());
+        for x in 0..image.width() {
+            for y in 0..image.height() {
+                columns.extend_from_slice(&image.get_pixel(x, y).0);
+            }
+        }
+
+        Self { image, columns }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        self.image
+            .get_pixel(x.min(self.width() - 1), y.min(self.height() - 1))
+            .0
+    }
+
+    // RGBA pixels top to bottom of the column at u, which wraps like in sample
+    pub fn column(&self, u: f64) -> &[u8] {
+        let x = ((u.rem_euclid(1.) * self.width() as f64) as usize).min(self.width() as usize - 1);
+        let size = self.height() as usize * 4;
+        &self.columns[x * size..(x + 1) * size]
+    }
+
+    // u and v are in 0..1 across the texture, wrapping outside of that
+    pub fn sample(&self, u: f64, v: f64) -> [u8; 4] {
+        let x = (u.rem_euclid(1.) * self.width() as f64) as u32;
+        let y = (v.rem_euclid(1.) * self.height() as f64) as u32;
+        self.get(x, y)
+    }
+}
+
+// atlas is a horizontal strip of square tiles, each as tall as the image
+pub fn load_atlas(path: &str) -> Result<Vec<Texture>> {
+    let img = image::open(path)
+        .map_err(|e| Error::image(path, e))?
+        .to_rgba8();
+    let size = img.height();
+
+    if size == 0 || img.width() < size {
+        return Err(Error::InvalidAtlas {
+            path: path.into(),
+            reason: format!(
+                "{}x{} is too narrow to hold a single square tile",
+                img.width(),
+                img.height()
+            ),
+        });
+    }
+
+    Ok((0..img.width() / size)
+        .map(|i| Texture::new(image::imageops::crop_imm(&img, i * size, 0, size, size).to_image()))
+        .collect())
+}
@@ -0,0 +1,449 @@
+// Maps made in the Tiled editor, saved as TMX or JSON. Only orthogonal, finite maps
+// whose layers are stored as CSV (or plain XML tiles in TMX) are read. Tile layers
+// are picked by name:
+//
+//     walls    any tile makes a full height wall, textured with that tile
+//     floor    the floor texture of each cell
+//     ceiling  the ceiling texture of each cell
+//
+// and other tile layers are ignored. Tile n of a tileset is texture n of the atlas.
+// In object layers, an object named or classed `spawn` is where the player starts,
+// facing along its rotation, an object with a `texture` property is a sprite, and
+// any other named object becomes a marker. A `name` property on the map names it.
+
+use std::path::Path;
+
+use roxmltree::Node;
+use serde_json::Value;
+
+use crate::{
+    error::{Error, Result},
+    map::{validate, Map, MapCell, MapCellType, Surface},
+    sprite::Sprite,
+    vector::Vector,
+};
+
+// walls only have their texture to go by, so the map view draws them all alike
+const WALL_COLOR: [u8; 4] = [200, 200, 200, 255];
+
+// the top bits of a gid say how the tile is flipped or rotated, which doesn't
+// matter for a whole cell
+const FLIP_FLAGS: u32 = 0xf000_0000;
+
+// far bigger than any hand made map, small enough that its cells fit in memory
+const MAX_CELLS: usize = 2048 * 2048;
+
+// the parts of a Tiled map that are used, whichever format it came from
+struct Tiled {
+    name: Option<String>,
+    width: usize,
+    height: usize,
+    tile_width: f64,
+    tile_height: f64,
+    first_gids: Vec<u32>,
+    layers: Vec<(String, Vec<u32>)>,
+    objects: Vec<Object>,
+}
+
+struct Object {
+    name: String,
+    class: String,
+    // the middle of the object, in pixels
+    x: f64,
+    y: f64,
+    rotation: f64,
+    properties: Vec<(String, String)>,
+}
+
+pub fn load_tiled(path: &str) -> Result<Map> {
+    let source = std::fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.into(),
+        source,
+    })?;
+
+    let tiled = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("tmx") => parse_tmx(&source),
+        _ => parse_json(&source),
+    };
+    let map = tiled
+        .and_then(Tiled::into_map)
+        .map_err(|reason| Error::InvalidMap {
+            path: path.into(),
+            reason,
+        })?;
+
+    validate(Path::new(path), &map.cells)?;
+    Ok(map)
+}
+
+impl Tiled {
+    fn into_map(self) -> std::result::Result<Map, String> {
+        // sizes come straight from the file, so nothing is allocated until they're known
+        // to be sane and every layer agrees with them
+        let size = self
+            .width
+            .checked_mul(self.height)
+            .filter(|size| (1..=MAX_CELLS).contains(size))
+            .ok_or_else(|| {
+                format!(
+                    "the map is {}x{}, it needs between 1 and {} cells",
+                    self.width, self.height, MAX_CELLS
+                )
+            })?;
+        if !self
+            .layers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("walls"))
+        {
+            return Err("there is no tile layer named walls".to_string());
+        }
+        for (name, gids) in &self.layers {
+            if gids.len() != size {
+                return Err(format!(
+                    "layer {:?} has {} tiles, the map is {}x{}",
+                    name,
+                    gids.len(),
+                    self.width,
+                    self.height
+                ));
+            }
+        }
+
+        let mut cells = vec![vec![MapCell::empty(); self.width]; self.height];
+        for (name, gids) in &self.layers {
+            let layer = name.to_lowercase();
+
+            for (i, &gid) in gids.iter().enumerate() {
+                let texture = match self.texture(gid) {
+                    Some(texture) => texture,
+                    None => continue,
+                };
+                let cell = &mut cells[i / self.width][i % self.width];
+
+                match layer.as_str() {
+                    "walls" => {
+                        cell.solid = MapCellType::Wall;
+                        cell.color = WALL_COLOR;
+                        cell.height = 1.;
+                        cell.texture = Some(texture);
+                    }
+                    "floor" => cell.floor = Some(Surface::Texture(texture)),
+                    "ceiling" => cell.ceiling = Some(Surface::Texture(texture)),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut map = Map::new(cells);
+        map.name = self.name;
+
+        for object in self.objects {
+            let pos = Vector::new(object.x / self.tile_width, object.y / self.tile_height);
+            let texture = object
+                .properties
+                .iter()
+                .find(|(name, _)| name == "texture")
+                .map(|(_, value)| value);
+
+            if object.name == "spawn" || object.class == "spawn" {
+                // rotation is clockwise in degrees, and y points down in both
+                let angle = object.rotation.to_radians();
+                map.spawn = Some((pos, Some(Vector::new(angle.cos(), angle.sin()))));
+            } else if let Some(texture) = texture {
+                let texture = texture.parse().map_err(|_| {
+                    format!(
+                        "sprite {:?} has texture {:?}, expected a number",
+                        object.name, texture
+                    )
+                })?;
+                map.sprites.push(Sprite::new(pos, texture));
+            } else if !object.name.is_empty() {
+                map.markers.push((object.name, pos));
+            }
+        }
+
+        Ok(map)
+    }
+
+    // the texture a gid stands for, counted from the start of its tileset
+    fn texture(&self, gid: u32) -> Option<usize> {
+        let gid = gid & !FLIP_FLAGS;
+        if gid == 0 {
+            return None;
+        }
+        let first = self
+            .first_gids
+            .iter()
+            .filter(|&&first| first <= gid)
+            .max()?;
+        Some((gid - first) as usize)
+    }
+}
+
+fn check_header(orientation: Option<&str>, infinite: bool) -> std::result::Result<(), String> {
+    match orientation {
+        Some("orthogonal") => {}
+        Some(other) => return Err(format!("it's {}, only orthogonal maps work", other)),
+        None => return Err("it has no orientation".to_string()),
+    }
+    if infinite {
+        return Err("it's an infinite map, only fixed size maps work".to_string());
+    }
+    Ok(())
+}
+
+// tile objects hang from their bottom left corner, everything else from the top left
+fn centre(x: f64, y: f64, width: f64, height: f64, tile: bool) -> (f64, f64) {
+    if tile {
+        (x + width / 2., y - height / 2.)
+    } else {
+        (x + width / 2., y + height / 2.)
+    }
+}
+
+fn parse_tmx(source: &str) -> std::result::Result<Tiled, String> {
+    let doc = roxmltree::Document::parse(source).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "map" {
+        return Err("the root element isn't <map>".to_string());
+    }
+    check_header(
+        root.attribute("orientation"),
+        root.attribute("infinite") == Some("1"),
+    )?;
+
+    let mut tiled = Tiled {
+        name: tmx_properties(root)
+            .into_iter()
+            .find(|(name, _)| name == "name")
+            .map(|(_, value)| value),
+        width: tmx_number(root, "width")? as usize,
+        height: tmx_number(root, "height")? as usize,
+        tile_width: tmx_number(root, "tilewidth")?,
+        tile_height: tmx_number(root, "tileheight")?,
+        first_gids: Vec::new(),
+        layers: Vec::new(),
+        objects: Vec::new(),
+    };
+
+    for tileset in root.children().filter(|n| n.has_tag_name("tileset")) {
+        tiled
+            .first_gids
+            .push(tmx_number(tileset, "firstgid")? as u32);
+    }
+    tmx_layers(root, &mut tiled)?;
+
+    Ok(tiled)
+}
+
+// layers can be nested in groups
+fn tmx_layers(parent: Node, tiled: &mut Tiled) -> std::result::Result<(), String> {
+    for node in parent.children().filter(Node::is_element) {
+        let name = node.attribute("name").unwrap_or_default().to_string();
+
+        match node.tag_name().name() {
+            "layer" => {
+                let data = node
+                    .children()
+                    .find(|n| n.has_tag_name("data"))
+                    .ok_or_else(|| format!("layer {:?} has no data", name))?;
+
+                let gids = match data.attribute("encoding") {
+                    Some("csv") => data
+                        .text()
+                        .unwrap_or_default()
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|gid| !gid.is_empty())
+                        .map(|gid| gid.parse::<u32>())
+                        .collect::<std::result::Result<Vec<_>, _>>(),
+                    None => data
+                        .children()
+                        .filter(|n| n.has_tag_name("tile"))
+                        .map(|tile| tile.attribute("gid").unwrap_or("0").parse::<u32>())
+                        .collect(),
+                    Some(encoding) => {
+                        return Err(format!(
+                            "layer {:?} is stored as {}, save it as CSV",
+                            name, encoding
+                        ))
+                    }
+                }
+                .map_err(|e| format!("layer {:?} has a bad tile, {}", name, e))?;
+
+                tiled.layers.push((name, gids));
+            }
+            "objectgroup" => {
+                for object in node.children().filter(|n| n.has_tag_name("object")) {
+                    let size = |attribute| tmx_number(object, attribute).unwrap_or(0.);
+                    let (x, y) = centre(
+                        tmx_number(object, "x")?,
+                        tmx_number(object, "y")?,
+                        size("width"),
+                        size("height"),
+                        object.has_attribute("gid"),
+                    );
+
+                    tiled.objects.push(Object {
+                        name: object.attribute("name").unwrap_or_default().to_string(),
+                        class: object
+                            .attribute("class")
+                            .or_else(|| object.attribute("type"))
+                            .unwrap_or_default()
+                            .to_string(),
+                        x,
+                        y,
+                        rotation: size("rotation"),
+                        properties: tmx_properties(object),
+                    });
+                }
+            }
+            "group" => tmx_layers(node, tiled)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn tmx_number(node: Node, attribute: &str) -> std::result::Result<f64, String> {
+    let value = node.attribute(attribute).ok_or_else(|| {
+        format!(
+            "<{}> has no {} attribute",
+            node.tag_name().name(),
+            attribute
+        )
+    })?;
+    value.parse().map_err(|_| {
+        format!(
+            "<{}> has {}={:?}, expected a number",
+            node.tag_name().name(),
+            attribute,
+            value
+        )
+    })
+}
+
+fn tmx_properties(node: Node) -> Vec<(String, String)> {
+    node.children()
+        .filter(|n| n.has_tag_name("properties"))
+        .flat_map(|properties| properties.children())
+        .filter(|n| n.has_tag_name("property"))
+        .map(|property| {
+            let value = property
+                .attribute("value")
+                .or_else(|| property.text())
+                .unwrap_or_default();
+            (
+                property.attribute("name").unwrap_or_default().to_string(),
+                value.to_string(),
+            )
+        })
+        .collect()
+}
+
+fn parse_json(source: &str) -> std::result::Result<Tiled, String> {
+    let root: Value = serde_json::from_str(source).map_err(|e| e.to_string())?;
+    check_header(root["orientation"].as_str(), root["infinite"] == true)?;
+
+    let mut tiled = Tiled {
+        name: json_properties(&root)
+            .into_iter()
+            .find(|(name, _)| name == "name")
+            .map(|(_, value)| value),
+        width: json_number(&root, "width")? as usize,
+        height: json_number(&root, "height")? as usize,
+        tile_width: json_number(&root, "tilewidth")?,
+        tile_height: json_number(&root, "tileheight")?,
+        first_gids: Vec::new(),
+        layers: Vec::new(),
+        objects: Vec::new(),
+    };
+
+    for tileset in root["tilesets"].as_array().into_iter().flatten() {
+        tiled
+            .first_gids
+            .push(json_number(tileset, "firstgid")? as u32);
+    }
+    json_layers(&root, &mut tiled)?;
+
+    Ok(tiled)
+}
+
+fn json_layers(parent: &Value, tiled: &mut Tiled) -> std::result::Result<(), String> {
+    for layer in parent["layers"].as_array().into_iter().flatten() {
+        let name = layer["name"].as_str().unwrap_or_default().to_string();
+
+        match layer["type"].as_str() {
+            Some("tilelayer") => {
+                let gids = match &layer["data"] {
+                    Value::Array(data) => data
+                        .iter()
+                        .map(|gid| gid.as_u64().map(|gid| gid as u32))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| format!("layer {:?} has a bad tile", name))?,
+                    Value::String(_) => {
+                        return Err(format!(
+                            "layer {:?} is stored as base64, save it as CSV",
+                            name
+                        ))
+                    }
+                    _ => return Err(format!("layer {:?} has no data", name)),
+                };
+
+                tiled.layers.push((name, gids));
+            }
+            Some("objectgroup") => {
+                for object in layer["objects"].as_array().into_iter().flatten() {
+                    let size = |key| json_number(object, key).unwrap_or(0.);
+                    let (x, y) = centre(
+                        json_number(object, "x")?,
+                        json_number(object, "y")?,
+                        size("width"),
+                        size("height"),
+                        object.get("gid").is_some(),
+                    );
+
+                    tiled.objects.push(Object {
+                        name: object["name"].as_str().unwrap_or_default().to_string(),
+                        class: object["class"]
+                            .as_str()
+                            .or_else(|| object["type"].as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                        x,
+                        y,
+                        rotation: size("rotation"),
+                        properties: json_properties(object),
+                    });
+                }
+            }
+            Some("group") => json_layers(layer, tiled)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn json_number(value: &Value, key: &str) -> std::result::Result<f64, String> {
+    value[key]
+        .as_f64()
+        .ok_or_else(|| format!("{} is missing or isn't a number", key))
+}
+
+fn json_properties(value: &Value) -> Vec<(String, String)> {
+    value["properties"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|property| {
+            let value = match &property["value"] {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (
+                property["name"].as_str().unwrap_or_default().to_string(),
+                value,
+            )
+        })
+        .collect()
+}
@@ -1,6 +1,6 @@
 use crate::raycaster::Direction;
 
-#[derive(Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
 pub struct Vector<T> {
     pub x: T,
     pub y: T,
@@ -33,6 +33,22 @@ impl<T> Vector<T> {
         y.atan2(x)
     }
 
+    pub fn length(&self) -> f64
+    where
+        T: Into<f64> + Copy,
+    {
+        let x = self.x.into();
+        let y = self.y.into();
+        (x * x + y * y).sqrt()
+    }
+
+    pub fn dot(&self, other: &Vector<T>) -> T
+    where
+        T: std::ops::Mul<Output = T> + std::ops::Add<Output = T> + Copy,
+    {
+        self.x * other.x + self.y * other.y
+    }
+
     pub fn orthogonal(&self, dir: Direction) -> Self
     where
         T: std::ops::Neg<Output = T> + Copy,
@@ -89,6 +105,20 @@ where
     }
 }
 
+impl<T> std::ops::Sub for Vector<T>
+where
+    T: std::ops::Sub<Output = T>,
+{
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+
 impl<T> std::ops::SubAssign for Vector<T>
 where
     T: std::ops::SubAssign,
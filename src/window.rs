@@ -1,46 +1,63 @@
-use pixels::{Error, Pixels, SurfaceTexture, PixelsBuilder};
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use twoderaycaster::Result;
 use winit::{
     dpi::LogicalSize,
     event_loop::EventLoop,
     window::{CursorGrabMode, Window, WindowBuilder},
 };
 
-use crate::{HEIGHT, WIDTH, SCALEFACTOR};
-
 pub struct GameWindow {
     pub window: Window,
     pub pixels: Pixels,
 }
 
 impl GameWindow {
-    pub fn new(title: &str, event_loop: &EventLoop<()>) -> Result<Self, Error> {
-        let size = LogicalSize::new(WIDTH, HEIGHT);
+    // the buffer is the resolution drawn at, upscaled to fill the window,
+    // or the window's own size if none is given
+    pub fn new(
+        title: &str,
+        event_loop: &EventLoop<()>,
+        window_size: (u32, u32),
+        buffer_size: Option<(u32, u32)>,
+        vsync: bool,
+    ) -> Result<Self> {
         let window = WindowBuilder::new()
             .with_title(title)
-            .with_inner_size(size)
-            .build(event_loop)
-            .unwrap();
-    
-        window
-            .set_cursor_grab(CursorGrabMode::Confined)
-            .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
-            .unwrap();
-        window.set_cursor_visible(false);
-
-        // let size = window.inner_size();
-    
-        let surface_texture = SurfaceTexture::new(WIDTH, HEIGHT, &window);
-        let pixels = PixelsBuilder::new(size.width, size.height, surface_texture)
-            .enable_vsync(true)
+            .with_inner_size(LogicalSize::new(window_size.0, window_size.1))
+            .build(event_loop)?;
+
+        let size = window.inner_size();
+
+        let (width, height) = buffer_size.unwrap_or((size.width, size.height));
+
+        let surface_texture = SurfaceTexture::new(size.width, size.height, &window);
+        let pixels = PixelsBuilder::new(width, height, surface_texture)
+            .enable_vsync(vsync)
             .build()?;
-    
-        Ok(Self {
-            window,
-            pixels,
-        })
-    }    
-
-    pub fn resize(&mut self, new_size: (u32, u32)) {
-        self.pixels.resize_surface(new_size.0, new_size.1).unwrap();
-    }    
+
+        let mut gw = Self { window, pixels };
+        gw.capture_cursor(true)?;
+        Ok(gw)
+    }
+
+    // the game turns with the mouse and hides it, the editor points with it
+    pub fn capture_cursor(&mut self, captured: bool) -> Result<()> {
+        if captured {
+            self.window
+                .set_cursor_grab(CursorGrabMode::Confined)
+                .or_else(|_e| self.window.set_cursor_grab(CursorGrabMode::Locked))?;
+        } else {
+            self.window.set_cursor_grab(CursorGrabMode::None)?;
+        }
+        self.window.set_cursor_visible(!captured);
+        Ok(())
+    }
+
+    pub fn resize(&mut self, new_size: (u32, u32)) -> Result<()> {
+        Ok(self.pixels.resize_surface(new_size.0, new_size.1)?)
+    }
+
+    pub fn resize_buffer(&mut self, new_size: (u32, u32)) -> Result<()> {
+        Ok(self.pixels.resize_buffer(new_size.0, new_size.1)?)
+    }
 }
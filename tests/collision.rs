@@ -0,0 +1,109 @@
+use twoderaycaster::{move_circle, Direction, MapCell, MapCellType, RayCaster, Vector};
+
+const RADIUS: f64 = 0.25;
+
+// '#' is a wall and anything else is empty; outside the rows is solid too
+fn solid<'a>(rows: &'a [&str]) -> impl Fn(i32, i32) -> bool + 'a {
+    move |x, y| {
+        if x < 0 || y < 0 {
+            return true;
+        }
+        rows.get(y as usize)
+            .and_then(|row| row.chars().nth(x as usize))
+            .is_none_or(|c| c == '#')
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn stops_a_radius_short_of_a_wall() {
+    let map = ["#####", "#...#", "#####"];
+    let pos = move_circle(
+        Vector::new(1.5, 1.5),
+        Vector::new(5., 0.),
+        RADIUS,
+        solid(&map),
+    );
+
+    assert!(close(pos.x, 4. - RADIUS), "{:?}", pos);
+    assert!(close(pos.y, 1.5));
+}
+
+#[test]
+fn slides_along_a_wall() {
+    let map = ["######", "#....#", "#....#", "######"];
+    let pos = move_circle(
+        Vector::new(1.5, 1.5),
+        Vector::new(1., 2.),
+        RADIUS,
+        solid(&map),
+    );
+
+    assert!(close(pos.x, 2.5), "{:?}", pos);
+    assert!(close(pos.y, 3. - RADIUS));
+}
+
+#[test]
+fn rounds_the_corner_of_a_pillar() {
+    let map = ["######", "#....#", "#.#..#", "#....#", "######"];
+
+    // clips the bottom of the pillar on the way past, and is nudged under it
+    let pos = move_circle(
+        Vector::new(1.5, 3.1),
+        Vector::new(2., 0.),
+        RADIUS,
+        solid(&map),
+    );
+
+    assert!(pos.x > 3., "{:?}", pos);
+    assert!(pos.y >= 3. + RADIUS - 1e-9, "{:?}", pos);
+}
+
+#[test]
+fn cannot_squeeze_between_walls_meeting_at_a_corner() {
+    let map = ["#####", "#.#.#", "##..#", "#####"];
+
+    // the only way from (1, 1) to (2, 2) is through the point where the walls touch
+    for motion in [
+        Vector::new(1., 1.),
+        Vector::new(0.7, 0.7),
+        Vector::new(3., 3.),
+    ] {
+        let pos = move_circle(Vector::new(1.5, 1.5), motion, RADIUS, solid(&map));
+
+        assert!(pos.x <= 2. - RADIUS + 1e-9, "{:?}", pos);
+        assert!(pos.y <= 2. - RADIUS + 1e-9, "{:?}", pos);
+    }
+}
+
+#[test]
+fn player_keeps_its_radius_from_walls() {
+    let map = ["#####", "#...#", "#...#", "#####"]
+        .iter()
+        .map(|row| {
+            row.chars()
+                .map(|c| match c {
+                    '#' => MapCell::new([200, 200, 200, 255], MapCellType::Wall, 1.),
+                    _ => MapCell::empty(),
+                })
+                .collect()
+        })
+        .collect();
+    let mut raycaster = RayCaster::with_map(map, 60.).unwrap();
+    raycaster.set_camera(Vector::new(2.5, 2.), Vector::new(1., 0.), 0.5);
+
+    // walk into the east wall for a second
+    for _ in 0..120 {
+        raycaster.change_direction(Direction::Up);
+        raycaster.update_player(1. / 120.);
+        raycaster.clear_input();
+    }
+
+    let radius = raycaster.movement().radius;
+    let pos = raycaster.position();
+    assert!(close(pos.x, 4. - radius), "{:?}", pos);
+    assert!(close(pos.y, 2.));
+}
@@ -0,0 +1,130 @@
+use twoderaycaster::{load_map, Direction, MapCellType, RayCaster, Side, Vector};
+
+fn raycaster(pos: (f64, f64), dir: (f64, f64)) -> RayCaster {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps/doors.txt");
+    let mut raycaster = RayCaster::with_map(load_map(path).unwrap().cells, 60.).unwrap();
+    let (pos, dir) = (Vector::new(pos.0, pos.1), Vector::new(dir.0, dir.1));
+    raycaster.set_camera(pos, dir, 0.5);
+    raycaster
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+// holds the key for walking forward, a tick at a time
+fn walk(raycaster: &mut RayCaster, secs: f64) {
+    for _ in 0..(secs * 120.) as usize {
+        raycaster.change_direction(Direction::Up);
+        raycaster.update_player(1. / 120.);
+        raycaster.clear_input();
+    }
+}
+
+#[test]
+fn text_maps_have_doors_and_push_walls() {
+    let raycaster = raycaster((2.5, 2.5), (1., 0.));
+
+    assert_eq!(raycaster.map()[2][4].solid, MapCellType::Door);
+    assert_eq!(raycaster.map()[4][2].solid, MapCellType::PushWall);
+    assert_eq!(raycaster.door_open(4, 2), Some(0.));
+    assert_eq!(raycaster.door_open(0, 0), None);
+}
+
+#[test]
+fn closed_doors_stand_half_way_into_their_cell() {
+    let raycaster = raycaster((2.5, 2.5), (1., 0.));
+    let hit = raycaster
+        .cast(Vector::new(2.5, 2.5), Vector::new(1., 0.), 10.)
+        .unwrap();
+
+    assert_eq!(hit.cell, Vector::new(4, 2));
+    assert_eq!(hit.side, Side::X);
+    assert!(close(hit.distance, 2.));
+    assert!(close(hit.point.x, 4.5));
+}
+
+#[test]
+fn rays_pass_the_open_part_of_a_door() {
+    let mut raycaster = raycaster((2.5, 2.5), (1., 0.));
+    assert!(raycaster.set_door(4, 2, true));
+    raycaster.update_doors(0.5);
+    assert_eq!(raycaster.door_open(4, 2), Some(0.5));
+
+    let past = raycaster
+        .cast(Vector::new(2.5, 2.25), Vector::new(1., 0.), 10.)
+        .unwrap();
+    assert_eq!(past.cell, Vector::new(9, 2));
+
+    // the texture slides along with the door
+    let door = raycaster
+        .cast(Vector::new(2.5, 2.75), Vector::new(1., 0.), 10.)
+        .unwrap();
+    assert_eq!(door.cell, Vector::new(4, 2));
+    assert!(close(door.u, 0.75), "{}", door.u);
+}
+
+#[test]
+fn doors_block_until_all_the_way_open() {
+    let mut raycaster = raycaster((2.5, 2.5), (1., 0.));
+    let radius = raycaster.movement().radius;
+
+    walk(&mut raycaster, 0.5);
+    assert!(close(raycaster.position().x, 4. - radius));
+
+    raycaster.set_door(4, 2, true);
+    raycaster.update_doors(0.9);
+    walk(&mut raycaster, 0.1);
+    assert!(close(raycaster.position().x, 4. - radius));
+
+    raycaster.update_doors(0.1);
+    walk(&mut raycaster, 0.2);
+    assert!(raycaster.position().x > 5.);
+}
+
+#[test]
+fn interact_opens_and_closes_the_door_ahead() {
+    let mut raycaster = raycaster((3.5, 2.5), (1., 0.));
+
+    assert!(raycaster.interact());
+    raycaster.update_doors(1.);
+    assert_eq!(raycaster.door_open(4, 2), Some(1.));
+
+    assert!(raycaster.interact());
+    raycaster.update_doors(0.25);
+    assert_eq!(raycaster.door_open(4, 2), Some(0.75));
+    raycaster.update_doors(1.);
+    assert_eq!(raycaster.door_open(4, 2), Some(0.));
+
+    // walls don't open, and doors don't close on the player
+    let mut raycaster = self::raycaster((3.5, 2.5), (0., -1.));
+    assert!(!raycaster.interact());
+    raycaster.set_camera(Vector::new(4.1, 2.5), Vector::new(1., 0.), 0.5);
+    raycaster.set_door(4, 2, true);
+    assert!(!raycaster.set_door(4, 2, false));
+}
+
+#[test]
+fn push_walls_slide_away_and_stay_open() {
+    let mut raycaster = raycaster((2.5, 2.5), (0., 1.));
+    assert!(raycaster.set_door(2, 4, true));
+    raycaster.update_doors(1.);
+
+    // half way, pushed away from the player
+    let hit = raycaster
+        .cast(Vector::new(2.5, 2.5), Vector::new(0., 1.), 10.)
+        .unwrap();
+    assert_eq!(hit.cell, Vector::new(2, 4));
+    assert_eq!(hit.side, Side::Y);
+    assert!(close(hit.distance, 2.));
+    assert!(!raycaster.set_door(2, 4, false));
+
+    raycaster.update_doors(1.);
+    let hit = raycaster
+        .cast(Vector::new(2.5, 2.5), Vector::new(0., 1.), 10.)
+        .unwrap();
+    assert_eq!(hit.cell, Vector::new(2, 6));
+
+    walk(&mut raycaster, 0.2);
+    assert!(raycaster.position().y > 5.);
+}
@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+
+use twoderaycaster::{
+    editor::MAX_HEIGHT, generate_map, load_map, Editor, Error, Framebuffer, Map, MapCell,
+    MapCellType, Pick, RayCaster, Vector,
+};
+
+fn setup() -> (RayCaster, Editor) {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps/room.png");
+    let mut raycaster = RayCaster::with_map(generate_map(path).unwrap(), 60.).unwrap();
+    raycaster
+        .set_spawn(Vector::new(3.5, 3.5), Vector::new(1., 0.))
+        .unwrap();
+    let editor = Editor::new(&raycaster, Map::new(Vec::new()));
+    (raycaster, editor)
+}
+
+fn cell(raycaster: &RayCaster, x: usize, y: usize) -> MapCell {
+    raycaster.map()[y][x]
+}
+
+#[test]
+fn paints_and_erases() {
+    let (mut raycaster, mut editor) = setup();
+    assert_eq!(cell(&raycaster, 2, 2).solid, MapCellType::Empty);
+
+    editor.select(1);
+    assert!(editor.paint(&mut raycaster, 2, 2));
+    assert_eq!(cell(&raycaster, 2, 2), editor.brush());
+
+    // painting the same wall again changes nothing
+    assert!(!editor.paint(&mut raycaster, 2, 2));
+
+    assert!(editor.erase(&mut raycaster, 2, 2));
+    assert_eq!(cell(&raycaster, 2, 2).solid, MapCellType::Empty);
+}
+
+#[test]
+fn keeps_walls_off_the_player_and_spawn() {
+    let (mut raycaster, mut editor) = setup();
+
+    assert!(!editor.paint(&mut raycaster, 3, 3));
+    assert_eq!(cell(&raycaster, 3, 3).solid, MapCellType::Empty);
+
+    // the player walks off, but the spawn stays behind
+    raycaster.set_camera(Vector::new(2.5, 3.5), Vector::new(1., 0.), 0.5);
+    assert!(!editor.paint(&mut raycaster, 3, 3));
+    assert!(!editor.paint(&mut raycaster, 2, 3));
+
+    editor.move_spawn(&raycaster, 4, 4).unwrap();
+    assert!(editor.paint(&mut raycaster, 3, 3));
+}
+
+#[test]
+fn undoes_and_redoes_whole_strokes() {
+    let (mut raycaster, mut editor) = setup();
+    let before = raycaster.map().to_vec();
+
+    for x in 1..5 {
+        editor.paint(&mut raycaster, x, 1);
+    }
+    editor.end_stroke();
+    editor.erase(&mut raycaster, 2, 1);
+    editor.end_stroke();
+    let after = raycaster.map().to_vec();
+
+    assert!(editor.undo(&mut raycaster));
+    assert_eq!(cell(&raycaster, 2, 1), editor.brush());
+    assert!(editor.undo(&mut raycaster));
+    assert_eq!(raycaster.map(), &before[..]);
+    assert!(!editor.undo(&mut raycaster));
+
+    assert!(editor.redo(&mut raycaster));
+    assert!(editor.redo(&mut raycaster));
+    assert_eq!(raycaster.map(), &after[..]);
+    assert!(!editor.redo(&mut raycaster));
+
+    // a new stroke drops what could have been redone
+    editor.undo(&mut raycaster);
+    editor.paint(&mut raycaster, 1, 2);
+    editor.end_stroke();
+    assert!(!editor.redo(&mut raycaster));
+}
+
+#[test]
+fn moves_the_spawn_to_empty_cells_only() {
+    let (mut raycaster, mut editor) = setup();
+
+    editor.move_spawn(&raycaster, 6, 2).unwrap();
+    assert_eq!(editor.spawn().0, Vector::new(6.5, 2.5));
+
+    let err = editor.move_spawn(&raycaster, 0, 0).unwrap_err();
+    assert!(matches!(err, Error::InvalidSpawn { .. }), "{}", err);
+    assert!(editor.move_spawn(&raycaster, 500, 2).is_err());
+
+    editor.undo(&mut raycaster);
+    assert_eq!(editor.spawn().0, Vector::new(3.5, 3.5));
+}
+
+#[test]
+fn brush_height_and_palette() {
+    let (_, mut editor) = setup();
+    let len = editor.palette().len();
+
+    // the map's walls come first, then every texture in the atlas, then a door and a
+    // push wall
+    let kinds: Vec<_> = editor.palette().iter().map(|c| c.solid).collect();
+    assert!(kinds[..len - 2].iter().all(|&k| k == MapCellType::Wall));
+    assert_eq!(kinds[len - 2..], [MapCellType::Door, MapCellType::PushWall]);
+    assert!((0..8).all(|i| editor.palette().iter().any(|c| c.texture == Some(i))));
+
+    editor.cycle(-1);
+    assert_eq!(editor.selected(), len - 1);
+    editor.cycle(1);
+    assert_eq!(editor.selected(), 0);
+
+    editor.adjust_height(100);
+    assert_eq!(editor.brush_height(), MAX_HEIGHT);
+    assert_eq!(editor.brush().height, MAX_HEIGHT);
+    editor.adjust_height(-100);
+    assert!(editor.brush_height() > 0.);
+
+    // choosing an entry goes back to its own height
+    editor.select(0);
+    assert_eq!(editor.brush_height(), editor.palette()[0].height);
+}
+
+#[test]
+fn brush_height_stays_in_range_for_tall_walls() {
+    let mut cells = vec![vec![MapCell::new([255, 0, 0, 255], MapCellType::Wall, 50.); 3]; 3];
+    cells[1][1] = MapCell::empty();
+    let mut raycaster = RayCaster::with_map(cells, 60.).unwrap();
+    raycaster
+        .set_spawn(Vector::new(1.5, 1.5), Vector::new(1., 0.))
+        .unwrap();
+
+    let mut editor = Editor::new(&raycaster, Map::new(Vec::new()));
+    assert_eq!(editor.palette()[0].height, 50.);
+    assert_eq!(editor.brush_height(), MAX_HEIGHT);
+    editor.cycle(1);
+    editor.select(0);
+    assert_eq!(editor.brush_height(), MAX_HEIGHT);
+
+    // the height gauge used to be drawn past its swatch and overflow
+    let mut frame = Framebuffer::new(480, 270);
+    editor.draw(&raycaster, &mut frame);
+}
+
+#[test]
+fn picks_every_cell_and_swatch() {
+    let (raycaster, editor) = setup();
+    let (width, height) = (480, 270);
+
+    let mut cells = HashSet::new();
+    let mut swatches = HashSet::new();
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            match editor.pick(&raycaster, (width, height), x, y) {
+                Some(Pick::Cell(x, y)) => cells.insert((x, y)),
+                Some(Pick::Swatch(i)) => swatches.insert(i),
+                None => false,
+            };
+        }
+    }
+
+    let map = raycaster.map();
+    assert_eq!(cells.len(), map.iter().map(Vec::len).sum::<usize>());
+    assert_eq!(swatches.len(), editor.palette().len());
+    assert_eq!(
+        editor.pick(&raycaster, (width, height), 0, height as usize - 1),
+        Some(Pick::Swatch(0))
+    );
+}
+
+#[test]
+fn saves_edits_with_the_spawn() {
+    let (mut raycaster, mut editor) = setup();
+    editor.paint(&mut raycaster, 2, 2);
+    editor.move_spawn(&raycaster, 4, 3).unwrap();
+
+    let path = std::env::temp_dir().join("twoderaycaster_edited.txt");
+    let path = path.to_str().unwrap();
+    editor.save(&raycaster, path).unwrap();
+
+    let saved = load_map(path).unwrap();
+    assert_eq!(saved.cells, raycaster.map());
+    assert_eq!(
+        saved.spawn,
+        Some((Vector::new(4.5, 3.5), Some(Vector::new(1., 0.))))
+    );
+}
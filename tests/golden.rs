@@ -0,0 +1,238 @@
+// Golden-image tests: fixed poses on fixed maps, compared against the reference
+// PNGs in tests/golden. Run with UPDATE_GOLDEN=1 to rewrite the references after
+// an intended change to the renderer; failures leave the actual frame and a diff
+// image in target/golden.
+
+use std::path::PathBuf;
+
+use image::{Rgba, RgbaImage};
+
+use twoderaycaster::{
+    debug, headless, load_map, Editor, Framebuffer, Map, Minimap, Pick, Projection, RayCaster,
+    Sprite, Vector,
+};
+
+// largest per-channel difference that still counts as a match
+const TOLERANCE: u8 = 2;
+
+// fraction of pixels allowed to differ by more than TOLERANCE
+const MAX_MISMATCH: f64 = 0.001;
+
+// small enough to keep the references light, big enough to show texture detail
+const WIDTH: u32 = 480;
+const HEIGHT: u32 = 270;
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn raycaster(map: &str, pos: (f64, f64), dir: (f64, f64)) -> RayCaster {
+    let path = root().join("tests/maps").join(map);
+    let map = load_map(path.to_str().unwrap()).unwrap();
+    let mut raycaster = RayCaster::with_map(map.cells, 60.).unwrap();
+    raycaster.set_camera(Vector::new(pos.0, pos.1), Vector::new(dir.0, dir.1), 0.5);
+    raycaster
+}
+
+fn assert_golden(name: &str, frame: &Framebuffer) {
+    let actual = frame.to_image();
+    let golden = root().join("tests/golden").join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&golden).unwrap();
+        return;
+    }
+
+    let expected = match image::open(&golden) {
+        Ok(img) => img.to_rgba8(),
+        Err(e) => panic!(
+            "{}: no reference image at {} ({}), run with UPDATE_GOLDEN=1 to create it",
+            name,
+            golden.display(),
+            e
+        ),
+    };
+
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "{}: frame size differs from the reference",
+        name
+    );
+
+    // mismatches in red over a faded copy of the reference
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut mismatched = 0;
+
+    for (x, y, want) in expected.enumerate_pixels() {
+        let got = actual.get_pixel(x, y);
+        let off = want
+            .0
+            .iter()
+            .zip(got.0.iter())
+            .any(|(a, b)| a.abs_diff(*b) > TOLERANCE);
+
+        if off {
+            mismatched += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            let luma = (want.0[0] as u32 + want.0[1] as u32 + want.0[2] as u32) / 3 / 4;
+            diff.put_pixel(x, y, Rgba([luma as u8, luma as u8, luma as u8, 255]));
+        }
+    }
+
+    let total = (actual.width() * actual.height()) as f64;
+    if mismatched as f64 / total > MAX_MISMATCH {
+        let out = root().join("target/golden");
+        std::fs::create_dir_all(&out).unwrap();
+
+        let actual_path = out.join(format!("{}.actual.png", name));
+        let diff_path = out.join(format!("{}.diff.png", name));
+        actual.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+
+        panic!(
+            "{}: {} pixels differ from the reference, see {} and {}",
+            name,
+            mismatched,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn walls_facing_corner() {
+    let raycaster = raycaster("room.png", (10.5, 5.5), (0.6, -0.8));
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("walls_facing_corner", &frame);
+}
+
+#[test]
+fn short_walls_and_pillar() {
+    let raycaster = raycaster("room.png", (9.5, 13.5), (-0.6, -0.8));
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("short_walls_and_pillar", &frame);
+}
+
+#[test]
+fn sprites_behind_short_wall() {
+    let mut raycaster = raycaster("room.png", (9.5, 13.5), (0., -1.));
+    raycaster.add_sprite(Sprite::new(Vector::new(9.5, 8.5), 1));
+    raycaster.add_sprite(Sprite::new(Vector::new(10.2, 12.), 0));
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("sprites_behind_short_wall", &frame);
+}
+
+#[test]
+fn fog_down_corridor() {
+    let raycaster = raycaster("corridor.png", (1.5, 2.5), (1., 0.));
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("fog_down_corridor", &frame);
+}
+
+#[test]
+fn fisheye_down_corridor() {
+    let mut raycaster = raycaster("corridor.png", (1.5, 2.5), (1., 0.));
+    raycaster.set_projection(Projection::Fisheye);
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("fisheye_down_corridor", &frame);
+}
+
+#[test]
+fn map_view() {
+    let raycaster = raycaster("room.png", (10.5, 5.5), (0.6, -0.8));
+    let frame = headless::render(&raycaster, true, WIDTH, HEIGHT).unwrap();
+    assert_golden("map_view", &frame);
+}
+
+#[test]
+fn retro_resolution() {
+    let raycaster = raycaster("room.png", (9.5, 13.5), (-0.6, -0.8));
+    let frame = headless::render(&raycaster, false, 160, 100).unwrap();
+    assert_golden("retro_resolution", &frame);
+}
+
+#[test]
+fn void_through_open_border() {
+    let mut raycaster = raycaster("open.png", (6.5, 8.5), (0.3, -0.95));
+    raycaster.set_void([60, 90, 140, 255]);
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("void_through_open_border", &frame);
+}
+
+#[test]
+fn max_distance_down_corridor() {
+    let mut raycaster = raycaster("corridor.png", (1.5, 2.5), (1., 0.));
+    raycaster.set_max_distance(20.);
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("max_distance_down_corridor", &frame);
+}
+
+#[test]
+fn text_map_legend() {
+    let raycaster = raycaster("hall.txt", (2.5, 4.5), (1., -0.2));
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("text_map_legend", &frame);
+}
+
+#[test]
+fn editor_view() {
+    let mut raycaster = raycaster("room.png", (3.5, 3.5), (1., 0.5));
+    let mut editor = Editor::new(&raycaster, Map::new(Vec::new()));
+    editor.select(3);
+    editor.paint(&mut raycaster, 2, 2);
+    editor.move_spawn(&raycaster, 6, 2).unwrap();
+    editor.hover(Some(Pick::Cell(4, 6)));
+
+    let mut frame = Framebuffer::new(WIDTH, HEIGHT);
+    editor.draw(&raycaster, &mut frame);
+    assert_golden("editor_view", &frame);
+}
+
+#[test]
+fn minimap_over_view() {
+    let mut raycaster = raycaster("room.png", (9.5, 13.5), (-0.6, -0.8));
+    raycaster.set_minimap(Some(Minimap::default()));
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("minimap_over_view", &frame);
+}
+
+#[test]
+fn minimap_rotated_with_player() {
+    let mut raycaster = raycaster("room.png", (9.5, 13.5), (-0.6, -0.8));
+    raycaster.set_minimap(Some(Minimap {
+        size: 0.5,
+        cells: 10.,
+        rotate: true,
+        opacity: 1.,
+    }));
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("minimap_rotated_with_player", &frame);
+}
+
+#[test]
+fn ray_debug_view() {
+    let raycaster = raycaster("room.png", (9.5, 13.5), (-0.6, -0.8));
+    let mut frame = Framebuffer::new(WIDTH, HEIGHT);
+    debug::draw_rays(&raycaster, &mut frame, 12);
+    assert_golden("ray_debug_view", &frame);
+}
+
+#[test]
+fn door_half_open() {
+    let mut raycaster = raycaster("doors.txt", (2.5, 2.5), (1., 0.1));
+    raycaster.set_door(4, 2, true);
+    raycaster.update_doors(0.5);
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("door_half_open", &frame);
+}
+
+#[test]
+fn push_wall_half_way() {
+    let mut raycaster = raycaster("doors.txt", (2.5, 2.5), (0.2, 1.));
+    raycaster.set_door(2, 4, true);
+    raycaster.update_doors(1.);
+    let frame = headless::render(&raycaster, false, WIDTH, HEIGHT).unwrap();
+    assert_golden("push_wall_half_way", &frame);
+}
@@ -0,0 +1,426 @@
+use twoderaycaster::{
+    generate_map, load_map, open_borders, save_map, save_map_png, Error, Map, MapCell, MapCellType,
+    RayCaster, Sprite, Surface, Vector,
+};
+
+// '#' is a wall and '.' is empty, rows can differ in length
+fn map(rows: &[&str]) -> Vec<Vec<MapCell>> {
+    rows.iter()
+        .map(|row| {
+            row.chars()
+                .map(|c| match c {
+                    '#' => MapCell::new([255, 0, 0, 255], MapCellType::Wall, 1.),
+                    _ => MapCell::empty(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// writes a map to a scratch file, since the loaders read from disk, and loads it by
+// its extension
+fn scratch_map(file_name: &str, source: &str) -> Result<Map, Error> {
+    let path = std::env::temp_dir().join(format!("twoderaycaster_{}", file_name));
+    std::fs::write(&path, source).unwrap();
+    load_map(path.to_str().unwrap())
+}
+
+// a path in the scratch directory that starts out empty, with no heightmap either
+fn scratch_path(file_name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("twoderaycaster_{}", file_name));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_file_name(format!(
+        "{}_height.png",
+        path.file_stem().unwrap().to_string_lossy()
+    )));
+    path.to_str().unwrap().to_string()
+}
+
+fn round_trip(map: &Map, file_name: &str) -> Map {
+    let path = scratch_path(file_name);
+    save_map(map, &path).unwrap();
+    load_map(&path).unwrap()
+}
+
+#[test]
+fn enclosed_map_has_no_open_borders() {
+    let map = map(&["#####", "#...#", "#.#.#", "#...#", "#####"]);
+    assert!(open_borders(&map).is_empty());
+}
+
+#[test]
+fn gaps_in_the_outer_wall_are_open() {
+    let map = map(&["##.##", "#...#", "....#", "#...#", "###.#"]);
+    assert_eq!(open_borders(&map), vec![(2, 0), (0, 2), (3, 4)]);
+}
+
+#[test]
+fn cells_past_a_shorter_row_are_open() {
+    let map = map(&["###", "#...#", "#####"]);
+    assert_eq!(open_borders(&map), vec![(3, 1)]);
+}
+
+#[test]
+fn test_maps() {
+    let path = |name: &str| format!("{}/tests/maps/{}", env!("CARGO_MANIFEST_DIR"), name);
+
+    assert!(open_borders(&generate_map(&path("room.png")).unwrap()).is_empty());
+    assert!(open_borders(&generate_map(&path("corridor.png")).unwrap()).is_empty());
+    assert!(!open_borders(&generate_map(&path("open.png")).unwrap()).is_empty());
+}
+
+#[test]
+fn missing_map_is_an_io_error() {
+    match generate_map("tests/maps/does_not_exist.png") {
+        Err(Error::Io { path, .. }) => assert!(path.ends_with("does_not_exist.png")),
+        Err(e) => panic!("expected an I/O error, got {}", e),
+        Ok(_) => panic!("expected an I/O error, got a map"),
+    }
+}
+
+#[test]
+fn non_image_map_is_a_decode_error() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+    assert!(matches!(generate_map(path), Err(Error::Image { .. })));
+}
+
+#[test]
+fn atlases_come_from_the_assets_directory_given() {
+    let cells = map(&["###", "#.#", "###"]);
+    assert!(RayCaster::with_assets(cells.clone(), 60., "assets").is_ok());
+    match RayCaster::with_assets(cells, 60., "tests/maps") {
+        Err(Error::Io { path, .. }) => assert!(path.ends_with("textures.png")),
+        Err(e) => panic!("expected an I/O error, got {}", e),
+        Ok(_) => panic!("expected an I/O error, got a raycaster"),
+    }
+}
+
+#[test]
+fn text_map_legend_and_metadata() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps/hall.txt");
+    let map = load_map(path).unwrap();
+
+    assert_eq!(map.name.as_deref(), Some("Hall"));
+    assert_eq!(
+        map.spawn,
+        Some((Vector::new(2.5, 4.5), Some(Vector::new(1., 0.))))
+    );
+    assert_eq!(map.marker("exit"), Some(Vector::new(10.5, 4.5)));
+    assert_eq!(map.marker("pillar"), Some(Vector::new(6.5, 2.5)));
+    assert_eq!(map.marker("entrance"), None);
+
+    assert_eq!(map.cells.len(), 8);
+    assert!(map.cells.iter().all(|row| row.len() == 12));
+    assert!(open_borders(&map.cells).is_empty());
+
+    let wall = map.cells[0][0];
+    assert_eq!(wall.solid, MapCellType::Wall);
+    assert_eq!(wall.color, [0x88, 0x44, 0x22, 255]);
+    assert_eq!(wall.texture, Some(1));
+    assert_eq!(wall.height, 1.);
+
+    assert_eq!(map.cells[4][6].height, 0.5);
+    assert_eq!(map.cells[2][6].texture, None);
+    assert_eq!(map.cells[1][1], MapCell::empty());
+
+    let patch = map.cells[4][7];
+    assert_eq!(patch.solid, MapCellType::Empty);
+    assert_eq!(patch.floor, Some(Surface::Color([64, 64, 64, 255])));
+    assert_eq!(patch.ceiling, Some(Surface::Texture(6)));
+}
+
+#[test]
+fn text_map_without_metadata() {
+    let map = scratch_map(
+        "bare.txt",
+        "[legend]\n'#' = wall\n' ' = empty\n\n[grid]\n###\n# #\n###\n\n",
+    )
+    .unwrap();
+
+    assert_eq!(map.name, None);
+    assert_eq!(map.spawn, None);
+    assert!(map.markers.is_empty());
+    assert_eq!(map.cells.len(), 3);
+    assert_eq!(map.cells[1][1].solid, MapCellType::Empty);
+}
+
+#[test]
+fn text_map_errors_name_the_line() {
+    let cases = [
+        (
+            "unknown_symbol",
+            "[legend]\n'#' = wall\n[grid]\n###\n#.#\n",
+            "line 5",
+        ),
+        (
+            "bad_color",
+            "[legend]\n'#' = wall color=red\n[grid]\n#\n",
+            "line 2",
+        ),
+        ("wide_key", "[legend]\n'##' = wall\n[grid]\n#\n", "line 2"),
+        (
+            "twice",
+            "[legend]\n'#' = wall\n'#' = empty\n[grid]\n#\n",
+            "line 3",
+        ),
+        (
+            "empty_color",
+            "[legend]\n'.' = empty color=#ffffff\n[grid]\n.\n",
+            "line 2",
+        ),
+        (
+            "bad_spawn",
+            "spawn = 1\n[legend]\n'#' = wall\n[grid]\n#\n",
+            "line 1",
+        ),
+        ("no_grid", "[legend]\n'#' = wall\n", "no [grid]"),
+        ("empty_grid", "[legend]\n'#' = wall\n[grid]\n\n", "line 3"),
+    ];
+
+    for (name, source, expected) in cases {
+        match scratch_map(&format!("{}.txt", name), source) {
+            Err(Error::InvalidMap { reason, .. }) => {
+                assert!(reason.contains(expected), "{}: {}", name, reason)
+            }
+            Err(e) => panic!("{}: expected an invalid map, got {}", name, e),
+            Ok(_) => panic!("{}: expected an invalid map, got a map", name),
+        }
+    }
+}
+
+#[test]
+fn tiled_layers_and_objects() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps/tiled.tmx");
+    let map = load_map(path).unwrap();
+
+    assert_eq!(map.name.as_deref(), Some("Tiled room"));
+    assert_eq!(map.cells.len(), 6);
+    assert!(map.cells.iter().all(|row| row.len() == 8));
+    assert!(open_borders(&map.cells).is_empty());
+
+    // gids count from the tileset's firstgid, and flip flags are ignored
+    assert_eq!(map.cells[0][0].texture, Some(0));
+    assert_eq!(map.cells[2][3].texture, Some(3));
+    assert_eq!(map.cells[5][7].texture, Some(1));
+    assert_eq!(map.cells[5][7].solid, MapCellType::Wall);
+    assert_eq!(map.cells[1][1].solid, MapCellType::Empty);
+
+    assert_eq!(map.cells[3][2].floor, Some(Surface::Texture(5)));
+    assert_eq!(map.cells[3][5].floor, None);
+    assert_eq!(map.cells[1][3].ceiling, Some(Surface::Texture(6)));
+
+    let (pos, dir) = map.spawn.unwrap();
+    assert_eq!(pos, Vector::new(1.5, 1.5));
+    let dir = dir.unwrap();
+    assert!(dir.x.abs() < 1e-9 && (dir.y - 1.).abs() < 1e-9);
+
+    assert_eq!(map.sprites, vec![Sprite::new(Vector::new(5.5, 3.5), 1)]);
+    assert_eq!(map.marker("exit"), Some(Vector::new(6.5, 2.5)));
+    assert_eq!(map.markers.len(), 1);
+}
+
+#[test]
+fn tiled_json_matches_tmx() {
+    let path = |name: &str| format!("{}/tests/maps/{}", env!("CARGO_MANIFEST_DIR"), name);
+    assert_eq!(
+        load_map(&path("tiled.json")).unwrap(),
+        load_map(&path("tiled.tmx")).unwrap()
+    );
+}
+
+#[test]
+fn tiled_maps_that_cant_be_read() {
+    let tmx = |attributes: &str, data: &str| {
+        format!(
+            r#"<map orientation="orthogonal" width="1" height="1" tilewidth="32" tileheight="32" {}>
+                <tileset firstgid="1"/>
+                <layer name="walls">{}</layer>
+            </map>"#,
+            attributes, data
+        )
+    };
+    let csv = r#"<data encoding="csv">1</data>"#;
+
+    let cases = [
+        (
+            "isometric.tmx",
+            tmx("", csv).replace("orthogonal", "isometric"),
+            "isometric",
+        ),
+        ("infinite.tmx", tmx(r#"infinite="1""#, csv), "infinite"),
+        (
+            "base64.tmx",
+            tmx("", r#"<data encoding="base64">AQAAAA==</data>"#),
+            "save it as CSV",
+        ),
+        (
+            "short.tmx",
+            tmx("", r#"<data encoding="csv"></data>"#),
+            "has 0 tiles",
+        ),
+        (
+            "huge.tmx",
+            tmx("", csv).replace(
+                r#"width="1" height="1""#,
+                r#"width="4294967296" height="4294967296""#,
+            ),
+            "the map is 4294967296x4294967296",
+        ),
+        (
+            "huge_layerless.tmx",
+            r#"<map orientation="orthogonal" width="3000000" height="3000000" tilewidth="32"
+                tileheight="32"><tileset firstgid="1"/></map>"#
+                .to_string(),
+            "the map is 3000000x3000000",
+        ),
+        (
+            "too_big.tmx",
+            tmx("", csv).replace(r#"width="1" height="1""#, r#"width="4096" height="4096""#),
+            "between 1 and",
+        ),
+        (
+            "short_layer.tmx",
+            tmx("", csv).replace(r#"height="1""#, r#"height="1000""#),
+            "has 1 tiles",
+        ),
+        (
+            "zero.tmx",
+            tmx("", r#"<data encoding="csv"></data>"#).replace(r#"width="1""#, r#"width="0""#),
+            "the map is 0x1",
+        ),
+        (
+            "no_walls.tmx",
+            tmx("", csv).replace("walls", "floor"),
+            "named walls",
+        ),
+        (
+            "base64.json",
+            r#"{"orientation": "orthogonal", "width": 1, "height": 1, "tilewidth": 32,
+                "tileheight": 32, "layers": [{"type": "tilelayer", "name": "walls",
+                "data": "AQAAAA=="}]}"#
+                .to_string(),
+            "save it as CSV",
+        ),
+    ];
+
+    for (name, source, expected) in cases {
+        match scratch_map(name, &source) {
+            Err(Error::InvalidMap { reason, .. }) => {
+                assert!(reason.contains(expected), "{}: {}", name, reason)
+            }
+            Err(e) => panic!("{}: expected an invalid map, got {}", name, e),
+            Ok(_) => panic!("{}: expected an invalid map, got a map", name),
+        }
+    }
+}
+
+#[test]
+fn text_maps_round_trip() {
+    let path = |name: &str| format!("{}/tests/maps/{}", env!("CARGO_MANIFEST_DIR"), name);
+
+    let hall = load_map(&path("hall.txt")).unwrap();
+    assert_eq!(round_trip(&hall, "hall_again.txt"), hall);
+
+    let doors = load_map(&path("doors.txt")).unwrap();
+    assert_eq!(round_trip(&doors, "doors_again.txt"), doors);
+
+    // sprites and flipped tiles from Tiled survive the text format too
+    let tiled = load_map(&path("tiled.tmx")).unwrap();
+    assert_eq!(round_trip(&tiled, "tiled_again.txt"), tiled);
+}
+
+#[test]
+fn built_maps_save_as_text() {
+    let mut map = Map::new(map(&["#####", "#...#", "#####"]));
+    map.cells[1][2].floor = Some(Surface::Color([10, 20, 30, 40]));
+    map.cells[1][3].ceiling = Some(Surface::Texture(4));
+    map.cells[0][1].height = 2.25;
+    map.cells[0][2].color = [1, 2, 3, 4];
+    map.name = Some("Built".to_string());
+    map.spawn = Some((Vector::new(1.5, 1.5), None));
+    map.markers
+        .push(("# not a comment".to_string(), Vector::new(3.5, 1.5)));
+
+    let path = scratch_path("built.txt");
+    save_map(&map, &path).unwrap();
+    assert_eq!(load_map(&path).unwrap(), map);
+
+    // the first kinds of wall and empty cell keep their usual symbols
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.ends_with("[grid]\n#AB##\n#.CD#\n#####\n"), "{}", text);
+}
+
+#[test]
+fn images_round_trip() {
+    // the bundled map has a heightmap, the test room keeps heights in alpha
+    let maps = [
+        (
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/map.png"),
+            "bundled.png",
+            true,
+        ),
+        (
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps/room.png"),
+            "room.png",
+            false,
+        ),
+    ];
+
+    for (original, file_name, heightmap) in maps {
+        let map = generate_map(original).unwrap();
+        let path = scratch_path(file_name);
+        save_map_png(&map, &path).unwrap();
+
+        assert_eq!(generate_map(&path).unwrap(), map, "{}", file_name);
+        let heightmap_path = path.replace(".png", "_height.png");
+        assert_eq!(std::path::Path::new(&heightmap_path).exists(), heightmap);
+    }
+}
+
+#[test]
+fn saving_heights_in_alpha_drops_an_old_heightmap() {
+    let path = scratch_path("reheight.png");
+    let mut map = map(&["###", "#.#", "###"]);
+
+    map[0][0].height = 2.;
+    save_map_png(&map, &path).unwrap();
+    assert_eq!(generate_map(&path).unwrap()[0][0].height, 2.);
+
+    map[0][0].height = 1.;
+    save_map_png(&map, &path).unwrap();
+    assert!(!std::path::Path::new(&path.replace(".png", "_height.png")).exists());
+    assert_eq!(generate_map(&path).unwrap()[0][0].height, 1.);
+}
+
+#[test]
+fn walls_too_tall_for_a_heightmap() {
+    let mut map = map(&["###", "#.#", "###"]);
+    map[1][0].height = 5.;
+
+    let path = scratch_path("too_tall.png");
+    assert!(matches!(
+        save_map_png(&map, &path),
+        Err(Error::InvalidMap { .. })
+    ));
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[test]
+fn doors_keep_their_height_in_a_heightmap() {
+    // the 2 high wall doesn't fit in alpha, so every height goes in the heightmap
+    let mut cells = map(&["####", "#..#", "####"]);
+    cells[0][1].height = 2.;
+    cells[1][3].solid = MapCellType::Door;
+    cells[2][2].solid = MapCellType::PushWall;
+
+    let path = scratch_path("doors_heightmap.png");
+    save_map_png(&cells, &path).unwrap();
+    assert!(std::path::Path::new(&path.replace(".png", "_height.png")).exists());
+
+    let loaded = generate_map(&path).unwrap();
+    assert_eq!(loaded[0][1].height, 2.);
+    for (x, y) in [(3, 1), (2, 2)] {
+        assert_eq!(loaded[y][x].solid, MapCellType::Wall, "({}, {})", x, y);
+        assert_eq!(loaded[y][x].height, 1., "({}, {})", x, y);
+    }
+}
@@ -0,0 +1,81 @@
+use twoderaycaster::{Gait, MovementController, Vector};
+
+const TICK: f64 = 1. / 120.;
+
+// velocity after holding wish for the given number of seconds
+fn hold(
+    controller: &MovementController,
+    vel: Vector<f64>,
+    wish: Vector<f64>,
+    secs: f64,
+) -> Vector<f64> {
+    (0..(secs / TICK) as usize).fold(vel, |vel, _| controller.accelerate(vel, wish, TICK))
+}
+
+#[test]
+fn reaches_and_holds_walk_speed() {
+    let controller = MovementController::default();
+    let vel = hold(&controller, Vector::new(0., 0.), Vector::new(1., 0.), 2.);
+
+    assert!((vel.x - controller.walk_speed).abs() < 1e-9);
+    assert_eq!(vel.y, 0.);
+}
+
+#[test]
+fn speeds_up_at_the_acceleration_rate() {
+    let controller = MovementController::default();
+    let vel = hold(&controller, Vector::new(0., 0.), Vector::new(0., 1.), 0.05);
+
+    assert!((vel.y - controller.acceleration * 0.05).abs() < 1e-9);
+}
+
+#[test]
+fn diagonal_is_no_faster_than_straight() {
+    let controller = MovementController::default();
+    let vel = hold(&controller, Vector::new(0., 0.), Vector::new(1., 1.), 2.);
+
+    assert!((vel.length() - controller.walk_speed).abs() < 1e-9);
+}
+
+#[test]
+fn gaits_clamp_to_their_own_speed() {
+    let mut controller = MovementController::default();
+    let wish = Vector::new(-1., 0.);
+
+    controller.set_gait(Gait::Sprint);
+    let vel = hold(&controller, Vector::new(0., 0.), wish, 2.);
+    assert!((vel.length() - controller.sprint_speed).abs() < 1e-9);
+
+    // dropping to a crouch slows down gradually rather than snapping
+    controller.set_gait(Gait::Crouch);
+    let slowing = controller.accelerate(vel, wish, TICK);
+    assert!(slowing.length() < controller.sprint_speed);
+    assert!(slowing.length() > controller.crouch_speed);
+
+    let vel = hold(&controller, vel, wish, 2.);
+    assert!((vel.length() - controller.crouch_speed).abs() < 1e-9);
+}
+
+#[test]
+fn stops_when_keys_are_released() {
+    let controller = MovementController::default();
+    let vel = hold(&controller, Vector::new(0., 0.), Vector::new(1., 0.), 2.);
+
+    let secs = controller.walk_speed / controller.deceleration;
+    let vel = hold(&controller, vel, Vector::new(0., 0.), secs + 0.1);
+    assert_eq!(vel, Vector::new(0., 0.));
+}
+
+#[test]
+fn controllers_are_per_instance() {
+    let mut fast = MovementController::default();
+    fast.walk_speed = 100.;
+    let slow = MovementController::default();
+
+    let wish = Vector::new(1., 0.);
+    let fast_vel = hold(&fast, Vector::new(0., 0.), wish, 2.);
+    let slow_vel = hold(&slow, Vector::new(0., 0.), wish, 2.);
+
+    assert!((fast_vel.x - 100.).abs() < 1e-9);
+    assert!((slow_vel.x - slow.walk_speed).abs() < 1e-9);
+}
@@ -0,0 +1,67 @@
+use twoderaycaster::{generate_map, Error, RayCaster, Vector};
+
+fn raycaster() -> RayCaster {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps/room.png");
+    RayCaster::with_map(generate_map(path).unwrap(), 60.).unwrap()
+}
+
+#[test]
+fn spawns_in_an_empty_cell() {
+    let mut raycaster = raycaster();
+    raycaster
+        .set_spawn(Vector::new(3.5, 3.5), Vector::new(0., 1.))
+        .unwrap();
+
+    assert_eq!(raycaster.position(), Vector::new(3.5, 3.5));
+    assert_eq!(raycaster.direction(), Vector::new(0., 1.));
+}
+
+#[test]
+fn refuses_walls_and_the_outside() {
+    let mut raycaster = raycaster();
+    let dir = Vector::new(1., 0.);
+
+    for pos in [
+        Vector::new(5.5, 5.5),
+        Vector::new(0.5, 3.),
+        Vector::new(-0.5, 3.),
+        Vector::new(3., 16.5),
+    ] {
+        let err = raycaster.set_spawn(pos, dir).unwrap_err();
+        assert!(matches!(err, Error::InvalidSpawn { .. }), "{}", err);
+    }
+}
+
+#[test]
+fn refuses_a_zero_direction() {
+    let mut raycaster = raycaster();
+    let err = raycaster
+        .set_spawn(Vector::new(3.5, 3.5), Vector::new(0., 0.))
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidSpawn { .. }));
+}
+
+#[test]
+fn refuses_directions_that_arent_finite() {
+    let mut raycaster = raycaster();
+    for dir in [
+        Vector::new(f64::NAN, 1.),
+        Vector::new(f64::INFINITY, 0.),
+        Vector::new(0., f64::NEG_INFINITY),
+    ] {
+        let err = raycaster.set_spawn(Vector::new(3.5, 3.5), dir).unwrap_err();
+        assert!(matches!(err, Error::InvalidSpawn { .. }), "{:?}", dir);
+    }
+}
+
+#[test]
+fn directions_come_back_unit_length() {
+    let mut raycaster = raycaster();
+    raycaster
+        .set_spawn(Vector::new(3.5, 3.5), Vector::new(-4., 0.))
+        .unwrap();
+    assert_eq!(raycaster.direction(), Vector::new(-1., 0.));
+
+    raycaster.set_camera(Vector::new(3.5, 3.5), Vector::new(3., 4.), 0.5);
+    assert!((raycaster.direction().length() - 1.).abs() < 1e-12);
+}
@@ -0,0 +1,46 @@
+use twoderaycaster::{generate_map, headless, Projection, RayCaster, Sprite, Vector};
+
+fn raycaster(projection: Projection) -> RayCaster {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps/room.png");
+    let mut raycaster = RayCaster::with_map(generate_map(path).unwrap(), 60.).unwrap();
+    raycaster.set_camera(Vector::new(9.5, 13.5), Vector::new(-0.3, -0.9), 0.5);
+    raycaster.set_projection(projection);
+    raycaster.add_sprite(Sprite::new(Vector::new(9.5, 8.5), 1));
+    raycaster.add_sprite(Sprite::new(Vector::new(8.2, 11.), 0));
+    raycaster
+}
+
+// every thread count has to give exactly the frame the single-threaded path does
+fn assert_identical(projection: Projection, width: u32, height: u32) {
+    let mut raycaster = raycaster(projection);
+    raycaster.set_threads(1);
+    let expected = headless::render(&raycaster, false, width, height).unwrap();
+
+    for threads in [2, 3, 7, 16] {
+        raycaster.set_threads(threads);
+        let actual = headless::render(&raycaster, false, width, height).unwrap();
+        assert!(
+            expected.pixels() == actual.pixels(),
+            "{:?} at {}x{} differs with {} threads",
+            projection,
+            width,
+            height,
+            threads
+        );
+    }
+}
+
+#[test]
+fn planar_matches_single_threaded() {
+    assert_identical(Projection::Planar, 480, 270);
+}
+
+#[test]
+fn fisheye_matches_single_threaded() {
+    assert_identical(Projection::Fisheye, 480, 270);
+}
+
+#[test]
+fn more_threads_than_columns() {
+    assert_identical(Projection::Planar, 5, 4);
+}
@@ -0,0 +1,124 @@
+use twoderaycaster::{MapCell, MapCellType, RayCaster, Side, Trace, Vector};
+
+// '#' is a wall, 'h' a half-height one and anything else is empty
+fn raycaster(rows: &[&str], pos: (f64, f64), dir: (f64, f64)) -> RayCaster {
+    let map = rows
+        .iter()
+        .map(|row| {
+            row.chars()
+                .map(|c| match c {
+                    '#' => MapCell::new([200, 200, 200, 255], MapCellType::Wall, 1.),
+                    'h' => MapCell::new([200, 200, 200, 255], MapCellType::Wall, 0.5),
+                    _ => MapCell::empty(),
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut raycaster = RayCaster::with_map(map, 60.).unwrap();
+    let (pos, dir) = (Vector::new(pos.0, pos.1), Vector::new(dir.0, dir.1));
+    raycaster.set_camera(pos, dir, 0.5);
+    raycaster
+}
+
+// the ray down the middle of a 100 column frame, which points straight ahead
+fn middle(raycaster: &RayCaster) -> Trace {
+    raycaster.trace(100, 50).swap_remove(50)
+}
+
+#[test]
+fn middle_ray_hits_the_wall_ahead() {
+    let raycaster = raycaster(&["#######", "#.....#", "#######"], (1.5, 1.5), (1., 0.));
+    let trace = middle(&raycaster);
+
+    assert_eq!(trace.hits.len(), 1);
+    let hit = trace.hits[0];
+    assert_eq!(hit.cell, Vector::new(6, 1));
+    assert_eq!(hit.side, Side::X);
+    assert_eq!(hit.distance, 4.5);
+    assert_eq!(hit.point, Vector::new(6., 1.5));
+    assert_eq!(trace.reach, 4.5);
+
+    let visited: Vec<_> = (2..=6).map(|x| Vector::new(x, 1)).collect();
+    assert_eq!(trace.visited, visited);
+}
+
+#[test]
+fn faces_looking_north_or_south_are_y_sides() {
+    let raycaster = raycaster(&["#####", "#...#", "#...#", "#####"], (2.5, 1.5), (0., 1.));
+    let hit = middle(&raycaster).hits[0];
+
+    assert_eq!(hit.cell, Vector::new(2, 3));
+    assert_eq!(hit.side, Side::Y);
+    assert_eq!(hit.distance, 1.5);
+}
+
+#[test]
+fn rays_carry_on_over_short_walls() {
+    let raycaster = raycaster(&["#######", "#..h..#", "#######"], (1.5, 1.5), (1., 0.));
+    let trace = middle(&raycaster);
+
+    let hits: Vec<_> = trace
+        .hits
+        .iter()
+        .map(|hit| (hit.cell, hit.distance))
+        .collect();
+    assert_eq!(hits, [(Vector::new(3, 1), 1.5), (Vector::new(6, 1), 4.5)]);
+    assert_eq!(trace.hits[0].wall.height, 0.5);
+    assert_eq!(trace.reach, 4.5);
+}
+
+#[test]
+fn rays_stop_where_they_leave_the_map() {
+    let raycaster = raycaster(&["#####", "#...", "#####"], (1.5, 1.5), (1., 0.));
+    let trace = middle(&raycaster);
+
+    assert!(trace.hits.is_empty());
+    assert_eq!(trace.reach, 2.5);
+}
+
+#[test]
+fn cast_finds_the_first_wall() {
+    let raycaster = raycaster(&["#######", "#..h..#", "#######"], (5.5, 1.5), (-1., 0.));
+    let hit = raycaster
+        .cast(Vector::new(1.5, 1.25), Vector::new(2., 0.), 10.)
+        .unwrap();
+
+    assert_eq!(hit.cell, Vector::new(3, 1));
+    assert_eq!(hit.side, Side::X);
+    assert_eq!(hit.distance, 1.5);
+    assert_eq!(hit.point, Vector::new(3., 1.25));
+    assert_eq!(hit.u, 0.75);
+}
+
+#[test]
+fn cast_distance_is_straight_line() {
+    let raycaster = raycaster(
+        &["#####", "#...#", "#...#", "#...#", "#####"],
+        (2.5, 2.5),
+        (1., 0.),
+    );
+    let hit = raycaster
+        .cast(Vector::new(1.5, 1.25), Vector::new(1., 1.), 10.)
+        .unwrap();
+
+    assert_eq!(hit.cell, Vector::new(4, 3));
+    assert_eq!(hit.side, Side::X);
+    assert!((hit.distance - 2.5 * 2f64.sqrt()).abs() < 1e-9);
+    assert!((hit.point.y - 3.75).abs() < 1e-9);
+}
+
+#[test]
+fn cast_misses_past_max_distance_and_the_map_edge() {
+    let raycaster = raycaster(&["#######", "#.....", "#######"], (1.5, 1.5), (1., 0.));
+
+    let up = raycaster.cast(Vector::new(1.5, 1.5), Vector::new(0., -1.), 0.4);
+    assert_eq!(up, None);
+    let hit = raycaster.cast(Vector::new(1.5, 1.5), Vector::new(0., -1.), 0.5);
+    assert_eq!(hit.map(|hit| hit.cell), Some(Vector::new(1, 0)));
+
+    let out = raycaster.cast(Vector::new(1.5, 1.5), Vector::new(1., 0.), 100.);
+    assert_eq!(out, None);
+    let still = raycaster.cast(Vector::new(1.5, 1.5), Vector::new(0., 0.), 100.);
+    assert_eq!(still, None);
+}